@@ -0,0 +1,26 @@
+module.exports = function(state, record) {
+    if (typeof this.assignees == 'undefined') {
+        this.assignees = [];
+    }
+    var assigned = typeof record.files[".type/Assigned"] !== 'undefined';
+    var unassigned = typeof record.files[".type/Unassigned"] !== 'undefined';
+    var changed = typeof record.files[".type/AssigneesChanged"] !== 'undefined';
+    if ((assigned || unassigned || changed) && typeof record.files.assignees !== 'undefined') {
+        var decoder = new TextDecoder("utf-8");
+        var assignees = decoder.decode(record.files.assignees).split("\n").filter(function(assignee) { return assignee.length > 0; });
+        if (changed) {
+            this.assignees = [];
+        }
+        for (var i = 0; i < assignees.length; i++) {
+            var index = this.assignees.indexOf(assignees[i]);
+            if (unassigned) {
+                if (index !== -1) {
+                    this.assignees.splice(index, 1);
+                }
+            } else if (index === -1) {
+                this.assignees.push(assignees[i]);
+            }
+        }
+    }
+    return Object.assign(state, {assignees: this.assignees});
+}
@@ -27,6 +27,7 @@ module.exports = function(state, record) {
         merge_request_report = merge_request_report || (!!record.files[".type/MergeRequestVerificationFailed"] ?
             "failure" : null);
         comments.push({
+            id: record.hash,
             text: decoder.decode(record.files.text),
             authors: decoder.decode(record.files[".authors"]),
             timestamp: decoder.decode(record.files[".timestamp"]),
@@ -37,5 +38,20 @@ module.exports = function(state, record) {
         state = Object.assign(state, {comments: comments});
     }
 
+    // Later revisions of a comment replace its text, the original authorship and time are kept
+    if (typeof record.files[".type/CommentEdited"] !== 'undefined') {
+        var comments = this.comments || [];
+        var decoder = new TextDecoder("utf-8");
+        var id = decoder.decode(record.files.comment);
+        comments.forEach(function(comment) {
+            if (comment.id === id) {
+                comment.text = decoder.decode(record.files.text);
+                comment.edited_at = decoder.decode(record.files[".timestamp"]);
+            }
+        });
+        this.comments = comments;
+        state = Object.assign(state, {comments: comments});
+    }
+
     return state;
 }
\ No newline at end of file
@@ -0,0 +1,33 @@
+module.exports = function(state, record) {
+    if (typeof this.labels == 'undefined') {
+        this.labels = [];
+        this.label_colors = {};
+    }
+    var tagged = typeof record.files[".type/Tagged"] !== 'undefined';
+    var untagged = typeof record.files[".type/Untagged"] !== 'undefined';
+    var changed = typeof record.files[".type/TagsChanged"] !== 'undefined';
+    if ((tagged || untagged || changed) && typeof record.files.labels !== 'undefined') {
+        var decoder = new TextDecoder("utf-8");
+        var names = decoder.decode(record.files.labels).split("\n").filter(function(name) { return name.length > 0; });
+        var colors = typeof record.files.colors !== 'undefined' ? decoder.decode(record.files.colors).split("\n") : [];
+        if (changed) {
+            this.labels = [];
+        }
+        for (var i = 0; i < names.length; i++) {
+            var index = this.labels.indexOf(names[i]);
+            if (untagged) {
+                if (index !== -1) {
+                    this.labels.splice(index, 1);
+                }
+            } else {
+                if (index === -1) {
+                    this.labels.push(names[i]);
+                }
+                if (typeof colors[i] !== 'undefined' && colors[i].length > 0) {
+                    this.label_colors[names[i]] = colors[i];
+                }
+            }
+        }
+    }
+    return Object.assign(state, {labels: this.labels, label_colors: this.label_colors});
+}
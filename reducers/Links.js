@@ -0,0 +1,22 @@
+module.exports = function(state, record) {
+    if (typeof record.files[".type/Linked"] === 'undefined') {
+        return state;
+    }
+    var decoder = new TextDecoder("utf-8");
+    var decode = function(name) {
+        return typeof record.files[name] !== 'undefined' ? decoder.decode(record.files[name]) : null;
+    };
+    var links = this.links || [];
+    links.push({
+        relation: decode("relation"),
+        // Present if the target was imported from the same repository
+        item: decode("item"),
+        url: decode("url"),
+        commit: decode("commit"),
+        text: decode("text"),
+        authors: decode(".authors"),
+        timestamp: decode(".timestamp"),
+    });
+    this.links = links;
+    return Object.assign(state, {links: links});
+}
@@ -0,0 +1,22 @@
+module.exports = function(state, record) {
+    var decoder = new TextDecoder("utf-8");
+    // Milestone item itself
+    if (typeof record.files[".type/Milestone"] !== 'undefined') {
+        var due = typeof record.files.due !== 'undefined' ? decoder.decode(record.files.due).trim() : null;
+        return Object.assign(state, {is_milestone: true, due: due});
+    }
+    // Issue or merge request linked to a milestone
+    if (typeof record.files[".type/Milestoned"] !== 'undefined') {
+        this.milestone = typeof record.files.milestone !== 'undefined' ? decoder.decode(record.files.milestone).trim() : null;
+    }
+    if (typeof record.files[".type/Demilestoned"] !== 'undefined') {
+        if (typeof record.files.milestone === 'undefined' ||
+            decoder.decode(record.files.milestone).trim() === this.milestone) {
+            this.milestone = null;
+        }
+    }
+    if (typeof this.milestone !== 'undefined') {
+        return Object.assign(state, {milestone: this.milestone});
+    }
+    return state;
+}
@@ -0,0 +1,32 @@
+module.exports = function(state, record) {
+    if (typeof record.files[".type/Reacted"] === 'undefined') {
+        return state;
+    }
+    var decoder = new TextDecoder("utf-8");
+    var decode = function(name) {
+        return typeof record.files[name] !== 'undefined' ? decoder.decode(record.files[name]) : null;
+    };
+    var reaction = decode("reaction");
+    // Comments are referred to by the hash of their record, reactions without one are to the issue itself
+    var comment = decode("comment");
+    // Every author counts once per kind of reaction
+    var key = [comment || "", reaction, decode(".authors") || record.hash].join("\n");
+    var reacted = this.reacted || {};
+    if (reacted[key]) {
+        return state;
+    }
+    reacted[key] = true;
+    this.reacted = reacted;
+    if (comment === null) {
+        var reactions = this.reactions || {};
+        reactions[reaction] = (reactions[reaction] || 0) + 1;
+        this.reactions = reactions;
+        return Object.assign(state, {reactions: reactions});
+    }
+    var comment_reactions = this.comment_reactions || {};
+    var counts = comment_reactions[comment] || {};
+    counts[reaction] = (counts[reaction] || 0) + 1;
+    comment_reactions[comment] = counts;
+    this.comment_reactions = comment_reactions;
+    return Object.assign(state, {comment_reactions: comment_reactions});
+}
@@ -0,0 +1,41 @@
+module.exports = function(state, record) {
+    var reviewed = typeof record.files[".type/Reviewed"] !== 'undefined';
+    var commented = typeof record.files[".type/ReviewCommented"] !== 'undefined';
+    if (!reviewed && !commented) {
+        return state;
+    }
+    var decoder = new TextDecoder("utf-8");
+    var decode = function(name) {
+        return typeof record.files[name] !== 'undefined' ? decoder.decode(record.files[name]) : null;
+    };
+    // Imported records are referred to by their original URL
+    var id = decode(".imported") || record.hash;
+    if (reviewed) {
+        var reviews = this.reviews || [];
+        reviews.push({
+            id: id,
+            state: decode("state"),
+            text: decode("text"),
+            authors: decode(".authors"),
+            timestamp: decode(".timestamp"),
+        });
+        this.reviews = reviews;
+        return Object.assign(state, {reviews: reviews});
+    }
+    var position = decode("position");
+    var review_comments = this.review_comments || [];
+    review_comments.push({
+        id: id,
+        review: decode("review"),
+        in_reply_to: decode("in_reply_to"),
+        path: decode("path"),
+        position: position === null ? null : parseInt(position, 10),
+        diff_hunk: decode("diff_hunk"),
+        commit: decode("commit"),
+        text: decode("text"),
+        authors: decode(".authors"),
+        timestamp: decode(".timestamp"),
+    });
+    this.review_comments = review_comments;
+    return Object.assign(state, {review_comments: review_comments});
+}
@@ -1,14 +1,29 @@
 module.exports = function(state, record) {
     if (typeof this.state == 'undefined') {
         this.state = 'open';
+        this.closer = null;
     }
     if (typeof record.files[".type/Closed"] !== 'undefined') {
+        var decoder = new TextDecoder("utf-8");
+        // What closed it, if known: the object ID of a commit or the URL of a pull request
+        // (and its item, if it was imported from the same repository)
+        var closer = null;
+        if (typeof record.files["closer/commit"] !== 'undefined') {
+            closer = {commit: decoder.decode(record.files["closer/commit"])};
+        } else if (typeof record.files["closer/pull-request"] !== 'undefined') {
+            closer = {pull_request: decoder.decode(record.files["closer/pull-request"])};
+            if (typeof record.files["closer/pull-request-item"] !== 'undefined') {
+                closer.pull_request_item = decoder.decode(record.files["closer/pull-request-item"]);
+            }
+        }
         this.state = 'closed';
-        return Object.assign(state, {state: 'closed'});
+        this.closer = closer;
+        return Object.assign(state, {state: 'closed', closer: closer});
     }
     if (typeof record.files[".type/Reopened"] !== 'undefined') {
         this.state = 'open';
-        return Object.assign(state, {state: 'open'});
+        this.closer = null;
+        return Object.assign(state, {state: 'open', closer: null});
     }
-    return Object.assign(state, {state: this.state});
-}
\ No newline at end of file
+    return Object.assign(state, {state: this.state, closer: this.closer});
+}
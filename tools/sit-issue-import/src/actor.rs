@@ -5,7 +5,7 @@ pub enum Actor {
     User(Author),
     #[serde(rename_all = "camelCase")]
     Bot {
-        login: String
+        url: String
     }
 }
 
@@ -14,7 +14,7 @@ impl<'a> Into<String> for &'a Actor {
     fn into(self) -> String {
         match self {
             &Actor::User(ref author) => author.into(),
-            &Actor::Bot { ref login } => format!("https://github.com/{}", login),
+            &Actor::Bot { ref url } => url.clone(),
         }
     }
 
@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use serde_json;
+use tar;
+use time;
+
+use author::Author;
+use error::Error;
+use github::milestone_name;
+use graphql::{PageHandler, MemoryPageHandler};
+use filter::Filter;
+use provider::{self, Kind, Item, Event, Label, Milestone, Closer};
+
+#[derive(Deserialize)]
+struct Email {
+    address: String,
+    primary: bool,
+}
+
+#[derive(Deserialize)]
+struct User {
+    url: String,
+    login: String,
+    name: Option<String>,
+    #[serde(default)]
+    emails: Vec<Email>,
+}
+
+#[derive(Deserialize)]
+struct RepositoryLabel {
+    name: String,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    url: String,
+    #[serde(default)]
+    labels: Vec<RepositoryLabel>,
+}
+
+/// Issue or pull request
+#[derive(Deserialize)]
+struct Issue {
+    url: String,
+    user: Option<String>,
+    title: String,
+    body: Option<String>,
+    #[serde(default)]
+    assignees: Vec<String>,
+    milestone: Option<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+    created_at: String,
+    updated_at: Option<String>,
+    closed_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IssueComment {
+    url: String,
+    issue: Option<String>,
+    pull_request: Option<String>,
+    user: Option<String>,
+    body: String,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct IssueEvent {
+    url: String,
+    issue: Option<String>,
+    pull_request: Option<String>,
+    actor: Option<String>,
+    event: String,
+    commit_id: Option<String>,
+    label_name: Option<String>,
+    label_color: Option<String>,
+    milestone_title: Option<String>,
+    title_was: Option<String>,
+    title_is: Option<String>,
+    /// Assignee of `assigned` and `unassigned` events
+    subject: Option<String>,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+struct ArchiveMilestone {
+    url: String,
+    user: Option<String>,
+    title: String,
+    description: Option<String>,
+    state: String,
+    due_on: Option<String>,
+    created_at: String,
+    updated_at: Option<String>,
+    closed_at: Option<String>,
+}
+
+/// Reads the top-level JSON files of an archive, either extracted into a directory or as a `.tar.gz` file
+fn read_files(path: &Path) -> Result<HashMap<String, Vec<u8>>, Error> {
+    let mut files = HashMap::new();
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".json") {
+                let mut buf = vec![];
+                fs::File::open(entry.path())?.read_to_end(&mut buf)?;
+                files.insert(name, buf);
+            }
+        }
+    } else {
+        let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(path)?));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().trim_left_matches("./").to_string();
+            if name.ends_with(".json") && !name.contains('/') {
+                let mut buf = vec![];
+                entry.read_to_end(&mut buf)?;
+                files.insert(name, buf);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Parses all files of a given kind (`issues_000001.json`, `issues_000002.json`, ...)
+fn records<T>(files: &HashMap<String, Vec<u8>>, kind: &str) -> Result<Vec<T>, Error> where for<'de> T: Deserialize<'de> {
+    let prefix = format!("{}_", kind);
+    let mut names: Vec<_> = files.keys().filter(|name| name.starts_with(&prefix)).collect();
+    names.sort();
+    let mut records = vec![];
+    for name in names {
+        let mut parsed: Vec<T> = serde_json::from_slice(&files[name])
+            .map_err(|err| Error::Archive(format!("{}: {}", name, err)))?;
+        records.append(&mut parsed);
+    }
+    Ok(records)
+}
+
+/// Converts archive timestamps (`2017-05-11T17:01:03.000-07:00`) into the format
+/// the API uses (`2017-05-12T00:01:03Z`), so that they stay comparable
+fn timestamp(s: &str) -> String {
+    let tm = match s.get(..19).and_then(|t| time::strptime(t, "%Y-%m-%dT%H:%M:%S").ok()) {
+        Some(tm) => tm,
+        None => return s.into(),
+    };
+    let zone = s[19..].trim_left_matches(|c: char| c == '.' || c.is_digit(10));
+    let offset = if zone.len() == 6 && (zone.starts_with('+') || zone.starts_with('-')) {
+        let hours: i64 = zone[1..3].parse().unwrap_or(0);
+        let minutes: i64 = zone[4..6].parse().unwrap_or(0);
+        if zone.starts_with('-') { -(hours * 3600 + minutes * 60) } else { hours * 3600 + minutes * 60 }
+    } else {
+        0
+    };
+    time::at_utc(tm.to_timespec() - time::Duration::seconds(offset))
+        .strftime("%Y-%m-%dT%H:%M:%SZ").map(|t| t.to_string()).unwrap_or_else(|_| s.into())
+}
+
+/// Last segment of a URL
+fn last_segment(url: &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+fn url_number(url: &str) -> usize {
+    last_segment(url).parse().unwrap_or(0)
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], s.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// GitHub migration archive
+///
+/// Everything is read upfront, so page handlers are not used.
+pub struct GitHubArchive {
+    path: String,
+    issues: Vec<Item>,
+    pull_requests: Vec<Item>,
+    milestones: Vec<Milestone>,
+    /// Comments and events by issue or pull request URL
+    timelines: HashMap<String, Vec<Event>>,
+}
+
+impl GitHubArchive {
+
+    fn read(path: &Path) -> Result<Self, Error> {
+        let files = read_files(path)?;
+
+        let users: HashMap<String, String> = records::<User>(&files, "users")?.into_iter().map(|user| {
+            let email = user.emails.iter().find(|e| e.primary).map(|e| e.address.clone());
+            let author = Author { login: user.login, url: user.url.clone(), name: user.name, email };
+            (user.url, (&author).into())
+        }).collect();
+        let user = |url: &Option<String>| url.as_ref().map(|url| users.get(url).cloned().unwrap_or_else(|| {
+            (&Author { login: last_segment(url).into(), url: url.clone(), name: None, email: None }).into()
+        }));
+
+        let repository = records::<Repository>(&files, "repositories")?.into_iter().next();
+        let path = match repository {
+            Some(ref repository) => {
+                let mut segments = repository.url.rsplit('/');
+                let name = segments.next().unwrap_or_default();
+                format!("{}/{}", segments.next().unwrap_or_default(), name)
+            },
+            None => return Err(Error::Archive("no repository found".into())),
+        };
+        let colors: HashMap<String, Option<String>> = repository.map(|r| r.labels).unwrap_or_default().into_iter()
+            .map(|label| (label.name, label.color)).collect();
+
+        let milestones: Vec<Milestone> = records::<ArchiveMilestone>(&files, "milestones")?.into_iter().map(|milestone| {
+            let created_at = timestamp(&milestone.created_at);
+            Milestone {
+                name: milestone_name(url_number(&milestone.url)),
+                author: user(&milestone.user),
+                closed: milestone.state == "closed",
+                title: milestone.title,
+                description: milestone.description.unwrap_or_default(),
+                due_on: milestone.due_on.as_ref().map(|t| timestamp(t)),
+                updated_at: milestone.updated_at.as_ref().map(|t| timestamp(t)).unwrap_or_else(|| created_at.clone()),
+                created_at,
+                closed_at: milestone.closed_at.as_ref().map(|t| timestamp(t)),
+                url: milestone.url,
+            }
+        }).collect();
+        let milestone_names: HashMap<String, String> = milestones.iter()
+            .map(|m| (m.title.clone(), m.name.clone())).collect();
+
+        let item = |kind: Kind, issue: Issue| {
+            let number = url_number(&issue.url);
+            let created_at = timestamp(&issue.created_at);
+            Item {
+                kind,
+                name: match kind {
+                    Kind::Issue => format!("github-issue-{}", number),
+                    Kind::MergeRequest => format!("github-pr-{}", number),
+                },
+                number,
+                author: user(&issue.user),
+                author_login: issue.user.as_ref().map(|url| last_segment(url).into()),
+                closed: issue.closed_at.is_some(),
+                title: issue.title,
+                body: issue.body.unwrap_or_default(),
+                edits: vec![],
+                reactions: vec![],
+                updated_at: issue.updated_at.as_ref().map(|t| timestamp(t)).unwrap_or_else(|| created_at.clone()),
+                created_at,
+                labels: Some(issue.labels.iter().map(|url| {
+                    let name = percent_decode(last_segment(url));
+                    Label { color: colors.get(&name).cloned().unwrap_or_default(), name }
+                }).collect()),
+                assignees: Some(issue.assignees.into_iter().filter_map(|url| user(&Some(url))).collect()),
+                milestone: issue.milestone.map(|url| milestone_name(url_number(&url))),
+                url: issue.url,
+            }
+        };
+        let mut issues: Vec<_> = records::<Issue>(&files, "issues")?.into_iter().map(|i| item(Kind::Issue, i)).collect();
+        issues.sort_by_key(|i| i.number);
+        let mut pull_requests: Vec<_> = records::<Issue>(&files, "pull_requests")?.into_iter().map(|i| item(Kind::MergeRequest, i)).collect();
+        pull_requests.sort_by_key(|i| i.number);
+
+        let mut timelines: HashMap<String, Vec<(String, Event)>> = HashMap::new();
+        for comment in records::<IssueComment>(&files, "issue_comments")? {
+            if let Some(target) = comment.issue.or(comment.pull_request) {
+                let created_at = timestamp(&comment.created_at);
+                timelines.entry(target).or_insert_with(Vec::new)
+                    .push((created_at.clone(), Event::Commented { url: comment.url, body: comment.body, created_at,
+                                                                  author: user(&comment.user), edits: vec![],
+                                                                  reactions: vec![] }));
+            }
+        }
+        for event in records::<IssueEvent>(&files, "issue_events")? {
+            let target = match event.issue.clone().or_else(|| event.pull_request.clone()) {
+                Some(target) => target,
+                None => continue,
+            };
+            let created_at = timestamp(&event.created_at);
+            let actor = user(&event.actor);
+            let url = event.url;
+            let converted = match (event.event.as_str(), event.label_name, event.milestone_title, event.subject) {
+                ("closed", _, _, _) =>
+                    Event::Closed { created_at: created_at.clone(), actor,
+                                    closer: event.commit_id.map(Closer::Commit) },
+                ("reopened", _, _, _) => Event::Reopened { created_at: created_at.clone(), actor },
+                ("locked", _, _, _) => Event::Locked { created_at: created_at.clone(), actor },
+                ("unlocked", _, _, _) => Event::Unlocked { created_at: created_at.clone(), actor },
+                ("merged", _, _, _) => Event::Merged { created_at: created_at.clone(), actor },
+                ("labeled", Some(name), _, _) =>
+                    Event::Labeled { url, created_at: created_at.clone(), actor, label: Label { name, color: event.label_color } },
+                ("unlabeled", Some(name), _, _) =>
+                    Event::Unlabeled { url, created_at: created_at.clone(), actor, label: Label { name, color: event.label_color } },
+                ("assigned", _, _, Some(assignee)) =>
+                    Event::Assigned { url, created_at: created_at.clone(), actor, assignee: user(&Some(assignee)).unwrap_or_default() },
+                ("unassigned", _, _, Some(assignee)) =>
+                    Event::Unassigned { url, created_at: created_at.clone(), actor, assignee: user(&Some(assignee)).unwrap_or_default() },
+                ("milestoned", _, Some(title), _) =>
+                    Event::Milestoned { url, created_at: created_at.clone(), actor, milestone: milestone_names.get(&title).cloned(), title },
+                ("demilestoned", _, Some(title), _) =>
+                    Event::Demilestoned { url, created_at: created_at.clone(), actor, milestone: milestone_names.get(&title).cloned(), title },
+                ("renamed", _, _, _) if event.title_was.is_some() && event.title_is.is_some() =>
+                    Event::Renamed { url, created_at: created_at.clone(), actor,
+                                     previous_title: event.title_was.unwrap(), current_title: event.title_is.unwrap() },
+                (other, _, _, _) => Event::Skipped { event: other.into() },
+            };
+            timelines.entry(target).or_insert_with(Vec::new).push((created_at, converted));
+        }
+        let timelines = timelines.into_iter().map(|(target, mut events)| {
+            // Stable, so that comments and events at the same time keep their order
+            events.sort_by(|a, b| a.0.cmp(&b.0));
+            (target, events.into_iter().map(|(_, event)| event).collect())
+        }).collect();
+
+        Ok(GitHubArchive { path, issues, pull_requests, milestones, timelines })
+    }
+
+    fn list(&self, kind: Kind) -> &[Item] {
+        match kind {
+            Kind::Issue => &self.issues,
+            Kind::MergeRequest => &self.pull_requests,
+        }
+    }
+
+}
+
+impl provider::Provider for GitHubArchive {
+
+    fn open(source: &str, _config: Option<&provider::Config>) -> Option<Result<Self, String>> {
+        let path = Path::new(source);
+        let recognized = if path.is_dir() {
+            path.join("schema.json").exists() || path.join("repositories_000001.json").exists()
+        } else {
+            path.is_file() && (source.ends_with(".tar.gz") || source.ends_with(".tgz"))
+        };
+        if !recognized {
+            return None;
+        }
+        Some(GitHubArchive::read(path).map_err(|err| format!("Can't read migration archive {}: {}", source, err)))
+    }
+
+    /// Same as the live GitHub source, as archives produce the same items
+    fn name(&self) -> &str {
+        "github"
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn count(&self, kind: Kind, since: Option<&String>, filter: &Filter) -> Result<usize, Error> {
+        Ok(self.items(kind, since, filter, Box::new(MemoryPageHandler::default())).count())
+    }
+
+    fn items<'a>(&'a self, kind: Kind, since: Option<&String>, filter: &Filter, _page_handler: Box<PageHandler>) -> Box<Iterator<Item = Result<Item, Error>> + 'a> {
+        let since = since.cloned();
+        let filter = filter.clone();
+        Box::new(self.list(kind).iter()
+                 .filter(move |i| since.as_ref().map(|since| i.updated_at >= *since).unwrap_or(true) && filter.matches(i))
+                 .map(|i| Ok(i.clone())))
+    }
+
+    fn milestones<'a>(&'a self) -> Box<Iterator<Item = Result<Milestone, Error>> + 'a> {
+        Box::new(self.milestones.iter().map(|m| Ok(m.clone())))
+    }
+
+    fn timeline<'a>(&'a self, item: &Item, _page_handler: Box<PageHandler>) -> Box<Iterator<Item = Result<Event, Error>> + 'a> {
+        match self.timelines.get(&item.url) {
+            Some(events) => Box::new(events.iter().map(|e| Ok(e.clone()))),
+            None => Box::new(::std::iter::empty()),
+        }
+    }
+
+    /// Archives don't include patches
+    fn patch(&self, _item: &Item) -> Result<Option<(&'static str, String)>, Error> {
+        Ok(None)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use provider::Provider;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(timestamp("2017-05-11T17:01:03.000-07:00"), "2017-05-12T00:01:03Z");
+        assert_eq!(timestamp("2017-05-11T17:01:03+02:00"), "2017-05-11T15:01:03Z");
+        assert_eq!(timestamp("2017-05-11T17:01:03Z"), "2017-05-11T17:01:03Z");
+    }
+
+    #[test]
+    fn read() {
+        let archive = GitHubArchive::open(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/github-archive"), None)
+            .unwrap().unwrap();
+        assert_eq!(archive.path(), "octo-org/octoroku");
+        assert_eq!(archive.count(Kind::Issue, None, &Filter::default()).unwrap(), 1);
+        assert_eq!(archive.count(Kind::Issue, Some(&"2017-05-13T00:00:00Z".into()), &Filter::default()).unwrap(), 0);
+        assert_eq!(archive.count(Kind::Issue, None, &Filter { closed: Some(true), ..Filter::default() }).unwrap(), 0);
+
+        let issue = archive.items(Kind::Issue, None, &Filter::default(), Box::new(MemoryPageHandler::default())).next().unwrap().unwrap();
+        assert_eq!(issue.name, "github-issue-1");
+        assert_eq!(issue.author, Some("Hubot (https://github.com/hubot) <hubot@example.com>".into()));
+        assert_eq!(issue.milestone, Some("github-milestone-1".into()));
+        let labels = issue.labels.as_ref().unwrap();
+        assert_eq!(labels[0].name, "good first issue");
+        assert_eq!(labels[0].color, Some("7057ff".into()));
+
+        let events: Vec<_> = archive.timeline(&issue, Box::new(::graphql::MemoryPageHandler::default()))
+            .map(|e| e.unwrap()).collect();
+        match (&events[0], &events[1], &events[2]) {
+            (&Event::Skipped { ref event }, &Event::Commented { .. }, &Event::Closed { ref closer, .. }) => {
+                assert_eq!(event, "subscribed");
+                assert_eq!(closer, &Some(Closer::Commit("abc123".into())));
+            },
+            other => panic!("unexpected events {:?}", other),
+        }
+    }
+
+}
@@ -2,6 +2,8 @@
 #[serde(rename_all = "camelCase")]
 pub struct Author {
     pub login: String,
+    /// Profile URL, on the host the author comes from
+    pub url: String,
     pub name: Option<String>,
     pub email: Option<String>,
 }
@@ -9,11 +11,11 @@ pub struct Author {
 impl<'a> Into<String> for &'a Author {
 
     fn into(self) -> String {
-        let name: String = self.name.as_ref().map(|s| s.clone()).unwrap_or("".into());
-        let url: String = format!("(https://github.com/{})", self.login);
-        let email: String = self.email.as_ref().map(|s| if s == "" { s.clone() } else { format!("<{}>", s) }).unwrap_or("".into());
+        let name: String = self.name.clone().unwrap_or_default();
+        let url: String = format!("({})", self.url);
+        let email: String = self.email.as_ref().map(|s| if s == "" { s.clone() } else { format!("<{}>", s) }).unwrap_or_default();
 
-        let elements: Vec<_> = vec![name, url, email].into_iter().filter(|s| s.len() > 0)
+        let elements: Vec<_> = vec![name, url, email].into_iter().filter(|s| !s.is_empty())
             .collect();
         elements.join(" ")
     }
@@ -35,12 +37,13 @@ impl<'a> Into<Files<&'a str, Box<Read>>> for &'a Author {
 mod tests {
 
     use super::*;
-    use serde_jsondd;
+    use serde_json;
 
     #[test]
     fn to_string() {
         let author = Author {
             login: "yrashk".into(),
+            url: "https://github.com/yrashk".into(),
             name: None,
             email: None,
         };
@@ -49,6 +52,7 @@ mod tests {
 
         let author = Author {
             login: "yrashk".into(),
+            url: "https://github.com/yrashk".into(),
             name: Some("Yurii".into()),
             email: None,
         };
@@ -57,6 +61,7 @@ mod tests {
 
         let author = Author {
             login: "yrashk".into(),
+            url: "https://github.com/yrashk".into(),
             name: Some("Yurii".into()),
             email: Some("foo@bar.com".into()),
         };
@@ -65,6 +70,7 @@ mod tests {
 
         let author = Author {
             login: "yrashk".into(),
+            url: "https://github.com/yrashk".into(),
             name: None,
             email: Some("foo@bar.com".into()),
         };
@@ -73,6 +79,7 @@ mod tests {
 
         let author = Author {
             login: "yrashk".into(),
+            url: "https://github.com/yrashk".into(),
             name: None,
             email: Some("".into()),
         };
@@ -81,6 +88,7 @@ mod tests {
 
         let author = Author {
             login: "yrashk".into(),
+            url: "https://github.com/yrashk".into(),
             name: Some("Yurii".into()),
             email: None,
         };
@@ -90,4 +98,10 @@ mod tests {
 
     }
 
+    #[test]
+    fn deserialize() {
+        let author: Author = serde_json::from_str(r#"{"login": "yrashk", "url": "https://github.com/yrashk", "name": "Yurii", "email": null}"#).unwrap();
+        assert_eq!(author, Author { login: "yrashk".into(), url: "https://github.com/yrashk".into(), name: Some("Yurii".into()), email: None });
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,234 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use time;
+
+/// Number of times a request is retried before giving up
+const MAX_RETRIES: u32 = 6;
+
+/// Used when the time of the rate limit reset is not known
+const DEFAULT_RESET_WAIT: u64 = 60;
+
+/// Rate limit status, as reported alongside every page
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimit {
+    pub remaining: i64,
+    pub reset_at: String,
+    pub cost: i64,
+}
+
+impl RateLimit {
+    fn seconds_until_reset(&self, now: time::Timespec) -> Option<u64> {
+        let reset_at = time::strptime(&self.reset_at, "%Y-%m-%dT%H:%M:%SZ").ok()?;
+        let seconds = (reset_at.to_timespec() - now).num_seconds();
+        // Allow for clock skew
+        Some(if seconds < 0 { 0 } else { seconds as u64 } + 1)
+    }
+}
+
+/// Tells the time and lets it pass, so that tests don't have to wait for real
+pub trait Clock: Send + Sync {
+    fn now(&self) -> time::Timespec;
+    fn sleep(&self, duration: Duration);
+}
+
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> time::Timespec {
+        time::get_time()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    rate_limit: Option<RateLimit>,
+    listener: Option<Box<FnMut(&str) + Send>>,
+}
+
+/// Holds requests back when the source asks to slow down
+///
+/// Clones share their state, so all requests made with the same token
+/// draw from the same budget.
+#[derive(Clone)]
+pub struct Backoff {
+    state: Arc<Mutex<State>>,
+    clock: Arc<Clock>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl Backoff {
+
+    pub fn with_clock(clock: Arc<Clock>) -> Self {
+        Backoff { state: Arc::default(), clock }
+    }
+
+    /// Sets a listener that gets a countdown message every second while waiting
+    ///
+    /// Without a listener, a single message is printed to stderr.
+    pub fn listen<F: FnMut(&str) + Send + 'static>(&self, listener: F) {
+        self.state.lock().unwrap().listener = Some(Box::new(listener));
+    }
+
+    /// Records the rate limit status reported with the last response
+    pub fn update(&self, rate_limit: RateLimit) {
+        self.state.lock().unwrap().rate_limit = Some(rate_limit);
+    }
+
+    /// Waits for the rate limit reset if the remaining budget wouldn't cover
+    /// another request like the last one
+    pub fn throttle(&self) {
+        let wait = match self.state.lock().unwrap().rate_limit {
+            Some(ref limit) if limit.remaining < limit.cost =>
+                Some(limit.seconds_until_reset(self.clock.now()).unwrap_or(DEFAULT_RESET_WAIT)),
+            _ => None,
+        };
+        if let Some(seconds) = wait {
+            self.wait("Rate limit exhausted", seconds);
+            self.state.lock().unwrap().rate_limit = None;
+        }
+    }
+
+    /// Waits for the rate limit reset after a request was rejected for exceeding it
+    ///
+    /// Returns `false` if the request shouldn't be retried anymore
+    pub fn wait_for_reset(&self, attempt: u32) -> bool {
+        if attempt >= MAX_RETRIES {
+            return false;
+        }
+        let seconds = self.state.lock().unwrap().rate_limit.take()
+            .and_then(|limit| limit.seconds_until_reset(self.clock.now()))
+            .unwrap_or(DEFAULT_RESET_WAIT);
+        self.wait("Rate limit exceeded", seconds);
+        true
+    }
+
+    /// Waits as long as the source asked to with `Retry-After`, or exponentially
+    /// longer with every attempt (starting from 0) if it didn't
+    ///
+    /// Returns `false` if the request shouldn't be retried anymore
+    pub fn retry(&self, attempt: u32, reason: &str, retry_after: Option<u64>) -> bool {
+        if attempt >= MAX_RETRIES {
+            return false;
+        }
+        self.wait(reason, retry_after.unwrap_or(1 << attempt));
+        true
+    }
+
+    fn wait(&self, reason: &str, seconds: u64) {
+        for left in (1..=seconds).rev() {
+            {
+                let mut state = self.state.lock().unwrap();
+                match state.listener {
+                    Some(ref mut listener) => listener(&format!("[ {}, resuming in {}s ] ", reason, left)),
+                    None if left == seconds => eprintln!("{}, resuming in {}s", reason, seconds),
+                    None => (),
+                }
+            }
+            self.clock.sleep(Duration::from_secs(1));
+        }
+    }
+
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    /// Clock that only moves when slept on
+    pub struct ManualClock {
+        now: Mutex<time::Timespec>,
+        slept: Mutex<u64>,
+    }
+
+    impl ManualClock {
+        pub fn new() -> Arc<Self> {
+            // 2018-05-01T12:00:00Z
+            Arc::new(ManualClock { now: Mutex::new(time::Timespec::new(1_525_176_000, 0)), slept: Mutex::new(0) })
+        }
+
+        /// Seconds slept in total
+        pub fn slept(&self) -> u64 {
+            *self.slept.lock().unwrap()
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> time::Timespec {
+            *self.now.lock().unwrap()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            *self.now.lock().unwrap() = self.now() + time::Duration::seconds(duration.as_secs() as i64);
+            *self.slept.lock().unwrap() += duration.as_secs();
+        }
+    }
+
+    fn rate_limit(remaining: i64, reset_at: &str) -> RateLimit {
+        RateLimit { remaining, reset_at: reset_at.into(), cost: 1 }
+    }
+
+    #[test]
+    fn throttle() {
+        let clock = ManualClock::new();
+        let backoff = Backoff::with_clock(clock.clone());
+        backoff.listen(|_| ());
+        backoff.throttle();
+        backoff.update(rate_limit(1, "2018-05-01T12:00:30Z"));
+        backoff.throttle();
+        assert_eq!(clock.slept(), 0);
+
+        // Exhausted budget is waited for until it's reset, plus a second for clock skew
+        backoff.update(rate_limit(0, "2018-05-01T12:00:30Z"));
+        backoff.throttle();
+        assert_eq!(clock.slept(), 31);
+        // Only once
+        backoff.throttle();
+        assert_eq!(clock.slept(), 31);
+    }
+
+    #[test]
+    fn wait_for_reset() {
+        let clock = ManualClock::new();
+        let backoff = Backoff::with_clock(clock.clone());
+        backoff.listen(|_| ());
+        backoff.update(rate_limit(0, "2018-05-01T12:00:10Z"));
+        assert!(backoff.wait_for_reset(0));
+        assert_eq!(clock.slept(), 11);
+        // The reset time that was waited for is forgotten
+        assert!(backoff.wait_for_reset(1));
+        assert_eq!(clock.slept(), 11 + DEFAULT_RESET_WAIT);
+        assert!(!backoff.wait_for_reset(MAX_RETRIES));
+        assert_eq!(clock.slept(), 11 + DEFAULT_RESET_WAIT);
+    }
+
+    #[test]
+    fn retry() {
+        let clock = ManualClock::new();
+        let backoff = Backoff::with_clock(clock.clone());
+        let messages = Arc::new(Mutex::new(vec![]));
+        let received = messages.clone();
+        backoff.listen(move |message| received.lock().unwrap().push(message.to_string()));
+        let attempts = (0..).take_while(|&attempt| backoff.retry(attempt, "Server responded with 502", None)).count();
+        assert_eq!(attempts as u32, MAX_RETRIES);
+        assert_eq!(clock.slept(), (1 << MAX_RETRIES) - 1);
+        assert_eq!(messages.lock().unwrap()[0], "[ Server responded with 502, resuming in 1s ] ");
+
+        // Unless the source tells how long to wait
+        assert!(backoff.retry(0, "Server responded with 403", Some(5)));
+        assert_eq!(clock.slept(), (1 << MAX_RETRIES) - 1 + 5);
+    }
+
+}
@@ -0,0 +1,121 @@
+use std::marker::PhantomData;
+use askama::Template;
+use graphql::{Query as GraphQLQuery, Pageable, HasPageInfo, HasTotalCount, PageInfo, Paged};
+use edits::UserContentEdit;
+use reactions::Reaction;
+use timeline::ReviewComment;
+
+/// Query for the pages of a list that didn't fit into the query it's nested in
+#[derive(Template)]
+#[template(path = "connection.graphql")]
+pub struct Query<T> {
+    /// Node ID of what the list belongs to
+    pub id: String,
+    /// GraphQL type of the node
+    pub on: &'static str,
+    /// Field of the list
+    pub field: &'static str,
+    pub after: Option<String>,
+    item: PhantomData<T>,
+}
+
+impl<T> Query<T> {
+    fn new(id: &str, on: &'static str, field: &'static str) -> Self {
+        Query { id: id.into(), on, field, after: None, item: PhantomData }
+    }
+}
+
+impl Query<UserContentEdit> {
+    pub fn edits(id: &str) -> Self {
+        Query::new(id, "UserContentEditable", "userContentEdits")
+    }
+}
+
+impl Query<Reaction> {
+    pub fn reactions(id: &str) -> Self {
+        Query::new(id, "Reactable", "reactions")
+    }
+}
+
+impl Query<ReviewComment> {
+    pub fn review_comments(id: &str) -> Self {
+        Query::new(id, "PullRequestReview", "comments")
+    }
+
+    pub fn thread_comments(id: &str) -> Self {
+        Query::new(id, "PullRequestReviewThread", "comments")
+    }
+}
+
+impl<T> GraphQLQuery for Query<T> {
+    type Result = Response<T>;
+    fn query(&self) -> String {
+        self.render().unwrap()
+    }
+}
+
+impl<T> Pageable for Query<T> {
+    type Item = T;
+    fn after(&self, cursor: String) -> Self {
+        Query { after: Some(cursor), ..Query::new(&self.id, self.on, self.field) }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionNode<T> {
+    connection: Paged<T>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Response<T> {
+    node: ConnectionNode<T>,
+}
+
+impl<T> HasPageInfo for Response<T> {
+    fn page_info(&self) -> &PageInfo {
+        &self.node.connection.page_info
+    }
+}
+
+impl<T> HasTotalCount for Response<T> {
+    fn total_count(&self) -> usize {
+        self.node.connection.total_count
+    }
+}
+
+impl<T> IntoIterator for Response<T> {
+    type Item = T;
+    type IntoIter = ::std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.node.connection.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn query() {
+        let text = GraphQLQuery::query(&Query::edits("MDU6SXNzdWUx").after("Y3Vyc29yOjI1".into()));
+        assert!(text.contains(r#"node(id: "MDU6SXNzdWUx")"#));
+        assert!(text.contains("... on UserContentEditable"));
+        assert!(text.contains(r#"connection: userContentEdits(first: 100, after: "Y3Vyc29yOjI1")"#));
+        assert!(text.contains("editedAt"));
+
+        let text = GraphQLQuery::query(&Query::reactions("MDU6SXNzdWUx"));
+        assert!(text.contains("... on Reactable"));
+        assert!(text.contains("connection: reactions(first: 100)"));
+        assert!(text.contains("content") && !text.contains("editedAt"));
+
+        let text = GraphQLQuery::query(&Query::thread_comments("MDIzOlB1bGxSZXF1ZXN0UmV2aWV3VGhyZWFkMQ"));
+        assert!(text.contains("... on PullRequestReviewThread"));
+        assert!(text.contains("connection: comments(first: 100)"));
+        assert!(text.contains("originalPosition"));
+    }
+
+}
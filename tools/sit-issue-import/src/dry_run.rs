@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// What an import would create, collected instead of writing records
+#[derive(Serialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub items: Vec<ItemReport>,
+    /// Number of records per record type
+    pub record_types: BTreeMap<String, usize>,
+    /// Number of timeline events per source event type that have no SIT counterpart
+    pub skipped_events: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemReport {
+    pub name: String,
+    /// `true` if the item is already present in the repository
+    pub exists: bool,
+    pub records: Vec<RecordReport>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordReport {
+    pub types: Vec<String>,
+    pub timestamp: Option<String>,
+    pub authors: Option<String>,
+    pub files: Vec<FileReport>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FileReport {
+    pub name: String,
+    pub size: usize,
+}
+
+impl Report {
+
+    /// Adds a record that would be created in the given item
+    pub fn record(&mut self, item: &str, exists: bool, files: &[(&str, Vec<u8>)]) {
+        let file = |name: &str| files.iter().find(|&&(n, _)| n == name)
+            .map(|&(_, ref buf)| String::from_utf8_lossy(buf).into_owned());
+        let types: Vec<String> = files.iter()
+            .filter_map(|&(name, _)| if name.starts_with(".type/") { Some(name[6..].into()) } else { None })
+            .collect();
+        for type_ in types.iter() {
+            *self.record_types.entry(type_.clone()).or_insert(0) += 1;
+        }
+        let record = RecordReport {
+            types,
+            timestamp: file(".timestamp"),
+            authors: file(".authors"),
+            files: files.iter().map(|&(name, ref buf)| FileReport { name: name.into(), size: buf.len() }).collect(),
+        };
+        match self.items.iter().position(|i| i.name == item) {
+            Some(index) => self.items[index].records.push(record),
+            None => self.items.push(ItemReport { name: item.into(), exists, records: vec![record] }),
+        }
+    }
+
+    /// Adds a timeline event that would be skipped
+    pub fn skipped(&mut self, event: &str) {
+        *self.skipped_events.entry(event.into()).or_insert(0) += 1;
+    }
+
+    /// Writes a human-readable summary
+    pub fn markdown<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "# Dry run\n")?;
+        if self.items.is_empty() {
+            writeln!(w, "Nothing would be imported.")?;
+        } else {
+            writeln!(w, "| Record type | Count |\n|---|---|")?;
+            for (type_, count) in self.record_types.iter() {
+                writeln!(w, "| {} | {} |", type_, count)?;
+            }
+        }
+        if !self.skipped_events.is_empty() {
+            writeln!(w, "\nTimeline events that would be skipped:\n\n| Event | Count |\n|---|---|")?;
+            for (event, count) in self.skipped_events.iter() {
+                writeln!(w, "| {} | {} |", event, count)?;
+            }
+        }
+        for item in self.items.iter() {
+            writeln!(w, "\n## {}{}\n", item.name, if item.exists { "" } else { " (new)" })?;
+            for record in item.records.iter() {
+                writeln!(w, "* {} at {}{}", record.types.join(", "),
+                         record.timestamp.as_ref().map(String::as_str).unwrap_or("unknown time"),
+                         record.authors.as_ref().map(|a| format!(" by {}", a)).unwrap_or_default())?;
+                for file in record.files.iter().filter(|f| !f.name.starts_with(".type/")) {
+                    writeln!(w, "  * `{}` ({} bytes)", file.name, file.size)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+}
@@ -0,0 +1,69 @@
+use actor::Actor;
+use graphql::Connection;
+use provider::Edit;
+
+/// Revision of the body of an issue, a pull request or a comment
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UserContentEdit {
+    pub edited_at: String,
+    /// Text as of this revision, absent if the revision was deleted
+    pub diff: Option<String>,
+    pub editor: Option<Actor>,
+}
+
+/// Splits the history of a text into its original version and later revisions, oldest first
+///
+/// Once a text is edited, GitHub lists its original version as the oldest revision.
+/// Deleted revisions are left out. Revisions are listed newest first, so unless all of
+/// them were fetched, the original version is unknown and only the current one is kept.
+pub fn history(current: String, edits: Option<Connection<UserContentEdit>>) -> (String, Vec<Edit>) {
+    let edits = match edits {
+        Some(ref edits) if edits.page_info.has_next_page => None,
+        edits => edits,
+    };
+    let mut edits: Vec<_> = edits.map(|edits| edits.nodes).unwrap_or_default().into_iter()
+        .filter_map(|UserContentEdit { edited_at, diff, editor }| {
+            let editor = editor.as_ref().map(Into::into);
+            diff.map(|text| Edit { edited_at, editor, text })
+        })
+        .collect();
+    edits.sort_by(|a, b| a.edited_at.cmp(&b.edited_at));
+    if edits.is_empty() {
+        (current, edits)
+    } else {
+        let original = edits.remove(0);
+        (original.text, edits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn original_first() {
+        let edits: Connection<UserContentEdit> = serde_json::from_str(r#"{"pageInfo": {"hasNextPage": false, "endCursor": null}, "nodes": [
+            {"editedAt": "2018-04-01T12:00:00Z", "diff": "Third", "editor": {"__typename": "Bot", "login": "hubot", "url": "https://github.com/apps/hubot"}},
+            {"editedAt": "2018-04-01T11:00:00Z", "diff": null, "editor": null},
+            {"editedAt": "2018-04-01T10:00:00Z", "diff": "First", "editor": null}
+        ]}"#).unwrap();
+        let (original, edits) = history("Third".into(), Some(edits));
+        assert_eq!(original, "First");
+        assert_eq!(edits.len(), 1);
+        assert_eq!((edits[0].text.as_str(), edits[0].editor.as_ref().map(String::as_str)), ("Third", Some("https://github.com/apps/hubot")));
+
+        assert_eq!(history("Never edited".into(), None), ("Never edited".into(), vec![]));
+    }
+
+    #[test]
+    fn incomplete() {
+        let edits: Connection<UserContentEdit> = serde_json::from_str(r#"{"pageInfo": {"hasNextPage": true, "endCursor": "Y3Vyc29yOjE="}, "nodes": [
+            {"editedAt": "2018-04-01T12:00:00Z", "diff": "Third", "editor": null}
+        ]}"#).unwrap();
+        assert_eq!(history("Third".into(), Some(edits)), ("Third".into(), vec![]));
+    }
+
+}
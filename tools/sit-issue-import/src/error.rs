@@ -0,0 +1,122 @@
+use std::{error, fmt, io};
+
+use reqwest;
+use serde_json;
+use sit_core;
+
+use pool::WorkerPanic;
+
+/// Error reported by a GraphQL endpoint
+#[derive(Deserialize, Debug)]
+pub struct GraphQLError {
+    pub message: String,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// HTTP request failed
+    Http(reqwest::Error),
+    /// Source responded with an unsuccessful status
+    Status {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+    /// GraphQL query returned errors instead of data
+    GraphQL(Vec<GraphQLError>),
+    /// Response doesn't have the expected shape
+    Deserialization(serde_json::Error),
+    /// SIT repository failure
+    Repository(sit_core::RepositoryError),
+    Io(io::Error),
+    /// Migration archive can't be understood
+    Archive(String),
+    /// Issue-tracking module can't be installed
+    Module(String),
+    /// Import thread panicked
+    Worker(Option<String>),
+    /// Error occurred while performing a request
+    Request {
+        /// GraphQL query or URL
+        request: String,
+        error: Box<Error>,
+    },
+    /// Error occurred while importing an issue, a merge request or a milestone
+    Item {
+        /// Number (where applicable) and URL of the item
+        item: String,
+        error: Box<Error>,
+    },
+}
+
+impl Error {
+
+    pub fn request<S: Into<String>>(request: S, error: Error) -> Self {
+        Error::Request { request: request.into(), error: Box::new(error) }
+    }
+
+    pub fn item<S: Into<String>>(item: S, error: Error) -> Self {
+        Error::Item { item: item.into(), error: Box::new(error) }
+    }
+
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Error::Http(ref err) => write!(f, "HTTP request failed: {}", err),
+            &Error::Status { ref status, ref body } => write!(f, "unexpected response status {}: {}", status, body.trim()),
+            &Error::GraphQL(ref errors) if errors.is_empty() => write!(f, "GraphQL query returned no data"),
+            &Error::GraphQL(ref errors) => {
+                let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+                write!(f, "GraphQL query failed: {}", messages.join("; "))
+            },
+            &Error::Deserialization(ref err) => write!(f, "unexpected response: {}", err),
+            &Error::Repository(ref err) => write!(f, "SIT repository error: {:?}", err),
+            &Error::Io(ref err) => write!(f, "I/O error: {}", err),
+            &Error::Archive(ref err) => write!(f, "invalid migration archive: {}", err),
+            &Error::Module(ref err) => write!(f, "can't install the issue-tracking module: {}", err),
+            &Error::Worker(Some(ref message)) => write!(f, "import thread panicked: {}", message),
+            &Error::Worker(None) => write!(f, "import thread panicked"),
+            &Error::Request { ref request, ref error } => write!(f, "{}\nRequest:\n{}", error, request.trim()),
+            &Error::Item { ref item, ref error } => write!(f, "failed to import {}: {}", item, error),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        "import error"
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Deserialization(err)
+    }
+}
+
+impl From<sit_core::RepositoryError> for Error {
+    fn from(err: sit_core::RepositoryError) -> Self {
+        Error::Repository(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<WorkerPanic> for Error {
+    fn from(panic: WorkerPanic) -> Self {
+        Error::Worker(panic.0)
+    }
+}
@@ -0,0 +1,172 @@
+use time;
+
+use provider::{Kind, Item};
+
+/// Which issues and merge requests to import
+///
+/// Providers narrow down their requests with it where the source supports
+/// that, and every listed item is checked with [`Filter::matches`] anyway.
+///
+/// Dates are UTC timestamps as returned by [`timestamp`]. Lower bounds are
+/// inclusive, upper bounds are exclusive.
+#[derive(Default, Clone, Debug)]
+pub struct Filter {
+    /// Only import issues or only merge requests
+    pub kind: Option<Kind>,
+    /// `Some(true)` for closed (including merged) items only, `Some(false)` for open ones only
+    pub closed: Option<bool>,
+    /// Only import items having any of these labels
+    pub labels: Vec<String>,
+    /// Only import items opened by this user (login or username)
+    pub author: Option<String>,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub updated_after: Option<String>,
+    pub updated_before: Option<String>,
+    /// Inclusive range of numbers to import
+    pub numbers: (Option<usize>, Option<usize>),
+}
+
+impl Filter {
+
+    /// Returns `true` if nothing is filtered out
+    pub fn is_empty(&self) -> bool {
+        self.kind.is_none() && self.closed.is_none() && self.labels.is_empty() && self.author.is_none() &&
+            self.created_after.is_none() && self.created_before.is_none() &&
+            self.updated_after.is_none() && self.updated_before.is_none() &&
+            self.numbers == (None, None)
+    }
+
+    /// Returns `true` if items of this kind are imported at all
+    pub fn includes(&self, kind: Kind) -> bool {
+        self.kind.map(|k| k == kind).unwrap_or(true)
+    }
+
+    /// The only number to import, if the range is that narrow
+    pub fn number(&self) -> Option<usize> {
+        match self.numbers {
+            (Some(from), Some(to)) if from == to => Some(from),
+            _ => None,
+        }
+    }
+
+    /// The later of the time of the last import and the lower bound of the update time
+    pub fn since(&self, since: Option<&String>) -> Option<String> {
+        match (since, self.updated_after.as_ref()) {
+            (Some(since), Some(after)) if after > since => Some(after.clone()),
+            (Some(since), _) => Some(since.clone()),
+            (None, after) => after.cloned(),
+        }
+    }
+
+    pub fn matches(&self, item: &Item) -> bool {
+        let after = |bound: &Option<String>, time: &str| bound.as_ref().map(|b| time >= b.as_str()).unwrap_or(true);
+        let before = |bound: &Option<String>, time: &str| bound.as_ref().map(|b| time < b.as_str()).unwrap_or(true);
+        self.includes(item.kind) &&
+            self.closed.map(|closed| item.closed == closed).unwrap_or(true) &&
+            (self.labels.is_empty() ||
+             item.labels.as_ref().map(|labels| labels.iter().any(|l| self.labels.contains(&l.name))).unwrap_or(false)) &&
+            self.author.as_ref().map(|author| item.author_login.as_ref() == Some(author)).unwrap_or(true) &&
+            after(&self.created_after, &item.created_at) && before(&self.created_before, &item.created_at) &&
+            after(&self.updated_after, &item.updated_at) && before(&self.updated_before, &item.updated_at) &&
+            self.numbers.0.map(|from| item.number >= from).unwrap_or(true) &&
+            self.numbers.1.map(|to| item.number <= to).unwrap_or(true)
+    }
+
+}
+
+/// Parses a date (`2018-03-01`) or a UTC timestamp (`2018-03-01T12:00:00Z`)
+pub fn timestamp(date: &str) -> Result<String, String> {
+    if time::strptime(date, "%Y-%m-%dT%H:%M:%SZ").is_ok() {
+        Ok(date.into())
+    } else if time::strptime(date, "%Y-%m-%d").is_ok() {
+        Ok(format!("{}T00:00:00Z", date))
+    } else {
+        Err(format!("invalid date: {}, expected YYYY-MM-DD or YYYY-MM-DDTHH:MM:SSZ", date))
+    }
+}
+
+/// Parses a range of numbers like `10..20`, `10..` or `..20`
+pub fn numbers(range: &str) -> Result<(Option<usize>, Option<usize>), String> {
+    let bound = |s: &str| if s.is_empty() { Ok(None) } else { s.parse().map(Some).map_err(|_| format!("invalid number: {}", s)) };
+    match range.find("..") {
+        Some(index) => Ok((bound(&range[..index])?, bound(&range[index + 2..])?)),
+        None => bound(range).map(|n| (n, n)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use provider::Label;
+
+    fn item(number: usize) -> Item {
+        Item {
+            kind: Kind::Issue,
+            name: format!("github-issue-{}", number),
+            number,
+            url: format!("https://github.com/sit-it/sit/issues/{}", number),
+            title: "Title".into(),
+            body: "".into(),
+            edits: vec![],
+            reactions: vec![],
+            created_at: "2018-03-01T10:00:00Z".into(),
+            updated_at: "2018-04-01T10:00:00Z".into(),
+            closed: false,
+            author: Some("(https://github.com/octocat)".into()),
+            author_login: Some("octocat".into()),
+            labels: Some(vec![Label { name: "bug".into(), color: None }]),
+            assignees: None,
+            milestone: None,
+        }
+    }
+
+    #[test]
+    fn matches() {
+        assert!(Filter::default().is_empty());
+        assert!(Filter::default().matches(&item(1)));
+        assert!(!Filter { kind: Some(Kind::MergeRequest), ..Filter::default() }.matches(&item(1)));
+        assert!(Filter { closed: Some(false), ..Filter::default() }.matches(&item(1)));
+        assert!(!Filter { closed: Some(true), ..Filter::default() }.matches(&item(1)));
+        assert!(Filter { labels: vec!["feature".into(), "bug".into()], ..Filter::default() }.matches(&item(1)));
+        assert!(!Filter { labels: vec!["feature".into()], ..Filter::default() }.matches(&item(1)));
+        assert!(Filter { author: Some("octocat".into()), ..Filter::default() }.matches(&item(1)));
+        assert!(!Filter { author: Some("hubot".into()), ..Filter::default() }.matches(&item(1)));
+        assert!(Filter { created_after: Some("2018-03-01T00:00:00Z".into()), created_before: Some("2018-03-02T00:00:00Z".into()),
+                         ..Filter::default() }
+                .matches(&item(1)));
+        assert!(!Filter { created_before: Some("2018-03-01T00:00:00Z".into()), ..Filter::default() }.matches(&item(1)));
+        assert!(!Filter { updated_after: Some("2018-05-01T00:00:00Z".into()), ..Filter::default() }.matches(&item(1)));
+        assert!(Filter { numbers: (Some(1), Some(1)), ..Filter::default() }.matches(&item(1)));
+        assert!(!Filter { numbers: (Some(2), None), ..Filter::default() }.matches(&item(1)));
+        assert_eq!(Filter { numbers: (Some(1), Some(1)), ..Filter::default() }.number(), Some(1));
+        assert_eq!(Filter { numbers: (Some(1), Some(2)), ..Filter::default() }.number(), None);
+    }
+
+    #[test]
+    fn since() {
+        let filter = Filter { updated_after: Some("2018-03-01T00:00:00Z".into()), ..Filter::default() };
+        assert_eq!(filter.since(None), Some("2018-03-01T00:00:00Z".into()));
+        assert_eq!(filter.since(Some(&"2018-04-01T00:00:00Z".into())), Some("2018-04-01T00:00:00Z".into()));
+        assert_eq!(filter.since(Some(&"2018-02-01T00:00:00Z".into())), Some("2018-03-01T00:00:00Z".into()));
+        assert_eq!(Filter::default().since(None), None);
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(timestamp("2018-03-01"), Ok("2018-03-01T00:00:00Z".into()));
+        assert_eq!(timestamp("2018-03-01T12:30:00Z"), Ok("2018-03-01T12:30:00Z".into()));
+        assert!(timestamp("March 1st").is_err());
+    }
+
+    #[test]
+    fn number_ranges() {
+        assert_eq!(numbers("10..20"), Ok((Some(10), Some(20))));
+        assert_eq!(numbers("10.."), Ok((Some(10), None)));
+        assert_eq!(numbers("..20"), Ok((None, Some(20))));
+        assert_eq!(numbers("7"), Ok((Some(7), Some(7))));
+        assert!(numbers("a..b").is_err());
+    }
+
+}
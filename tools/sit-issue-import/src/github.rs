@@ -0,0 +1,748 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+use regex::Regex;
+use reqwest;
+use serde::Deserialize;
+
+use graphql::{PageableRequest, PageHandler, MemoryPageHandler, Pageable, Query, PageInfo, Position, Connection};
+use error::Error;
+use filter::Filter;
+use backoff::Backoff;
+use token;
+use provider::{self, Kind, Item, Event, Milestone, Timeline};
+use {issues, pull_requests, timeline, milestones, connection, edits, reactions};
+
+const GITHUB_HOST : &str = "github.com";
+const GITHUB_GRAPHQL : &str = "https://api.github.com/graphql";
+/// Number of timelines whose first pages are fetched in one request
+const TIMELINE_BATCH : usize = 25;
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub token: Option<String>,
+    /// GitHub Enterprise Server instances by host name
+    #[serde(default)]
+    pub hosts: HashMap<String, HostConfig>,
+}
+
+#[derive(Deserialize)]
+pub struct HostConfig {
+    /// Token for this host, if it differs from the top-level one
+    pub token: Option<String>,
+    /// GraphQL endpoint, `https://HOST/api/graphql` by default
+    pub api: Option<String>,
+}
+
+pub struct GitHub {
+    /// GraphQL endpoint
+    api: String,
+    /// Base URL of the web interface
+    web: String,
+    token: String,
+    owner: String,
+    repository: String,
+    path: String,
+    client: reqwest::Client,
+    /// Issue or pull request the source URL points to
+    selection: Option<(Kind, usize)>,
+    /// Milestone numbers by title, loaded when first needed
+    milestone_numbers: Mutex<Option<HashMap<String, usize>>>,
+    /// Single issues and pull requests fetched already
+    fetched: Mutex<HashMap<(Kind, usize), Vec<Item>>>,
+    backoff: Backoff,
+}
+
+pub fn milestone_name(number: usize) -> String {
+    format!("github-milestone-{}", number)
+}
+
+pub fn item_name(kind: Kind, number: usize) -> String {
+    match kind {
+        Kind::Issue => format!("github-issue-{}", number),
+        Kind::MergeRequest => format!("github-pr-{}", number),
+    }
+}
+
+impl From<issues::Issue> for Item {
+    fn from(issue: issues::Issue) -> Self {
+        let (body, edits) = edits::history(issue.body, issue.user_content_edits);
+        let reactions = reactions::reactions(&issue.url, issue.reactions);
+        Item {
+            kind: Kind::Issue,
+            name: item_name(Kind::Issue, issue.number),
+            number: issue.number,
+            author: issue.author.as_ref().map(Into::into),
+            author_login: issue.author.as_ref().map(|a| a.login.clone()),
+            closed: issue.state != "OPEN",
+            url: issue.url,
+            title: issue.title,
+            body,
+            edits,
+            reactions,
+            created_at: issue.created_at,
+            updated_at: issue.updated_at,
+            labels: Some(issue.labels.nodes),
+            assignees: Some(issue.assignees.nodes.iter().map(Into::into).collect()),
+            milestone: issue.milestone.map(|m| milestone_name(m.number)),
+        }
+    }
+}
+
+impl From<pull_requests::PullRequest> for Item {
+    fn from(pr: pull_requests::PullRequest) -> Self {
+        let (body, edits) = edits::history(pr.body, pr.user_content_edits);
+        let reactions = reactions::reactions(&pr.url, pr.reactions);
+        Item {
+            kind: Kind::MergeRequest,
+            name: item_name(Kind::MergeRequest, pr.number),
+            number: pr.number,
+            author: pr.author.as_ref().map(Into::into),
+            author_login: pr.author.as_ref().map(|a| a.login.clone()),
+            closed: pr.state != "OPEN",
+            url: pr.url,
+            title: pr.title,
+            body,
+            edits,
+            reactions,
+            created_at: pr.created_at,
+            updated_at: pr.updated_at,
+            labels: Some(pr.labels.nodes),
+            assignees: Some(pr.assignees.nodes.iter().map(Into::into).collect()),
+            milestone: pr.milestone.map(|m| milestone_name(m.number)),
+        }
+    }
+}
+
+impl From<milestones::Milestone> for Milestone {
+    fn from(milestone: milestones::Milestone) -> Self {
+        Milestone {
+            name: milestone_name(milestone.number),
+            author: milestone.creator.as_ref().map(Into::into),
+            url: milestone.url,
+            title: milestone.title,
+            description: milestone.description.unwrap_or_default(),
+            due_on: milestone.due_on,
+            closed: milestone.state == "CLOSED",
+            created_at: milestone.created_at,
+            updated_at: milestone.updated_at,
+            closed_at: milestone.closed_at,
+        }
+    }
+}
+
+impl GitHub {
+
+    /// Imports from a stand-in endpoint
+    #[cfg(test)]
+    pub fn new<S: Into<String>>(api: S, owner: S, repository: S) -> Self {
+        let (owner, repository) = (owner.into(), repository.into());
+        GitHub { api: api.into(), web: format!("https://{}", GITHUB_HOST), token: "token".into(), path: format!("{}/{}", owner, repository), owner, repository,
+                 selection: None, client: reqwest::Client::new(), milestone_numbers: Mutex::new(None), fetched: Mutex::new(HashMap::new()),
+                 backoff: Backoff::default() }
+    }
+
+    fn request<Q: Pageable + Query, P: PageHandler>(&self, query: Q, page_handler: P) -> PageableRequest<&str, Q, P> {
+        PageableRequest::new(self.client.clone(), &self.api, &self.token, query, page_handler, self.backoff.clone())
+    }
+
+    /// Fetches the rest of a list that didn't fit into the query it's nested in
+    fn complete<T>(&self, list: Option<&mut Connection<T>>, query: connection::Query<T>) -> Result<(), Error>
+        where T: Debug, for<'de> T: Deserialize<'de> {
+        if let Some(list) = list {
+            if let Some(cursor) = list.page_info.end_cursor.clone() {
+                if list.page_info.has_next_page {
+                    for node in self.request(query.after(cursor), MemoryPageHandler::default()) {
+                        list.nodes.push(node?);
+                    }
+                    list.page_info = PageInfo::default();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn issue(&self, mut issue: issues::Issue) -> Result<Item, Error> {
+        self.complete(issue.user_content_edits.as_mut(), connection::Query::edits(&issue.id))?;
+        self.complete(issue.reactions.as_mut(), connection::Query::reactions(&issue.id))?;
+        Ok(issue.into())
+    }
+
+    fn pull_request(&self, mut pr: pull_requests::PullRequest) -> Result<Item, Error> {
+        self.complete(pr.user_content_edits.as_mut(), connection::Query::edits(&pr.id))?;
+        self.complete(pr.reactions.as_mut(), connection::Query::reactions(&pr.id))?;
+        Ok(pr.into())
+    }
+
+    /// Finds the SIT item of a milestone by its title
+    fn milestone(&self, title: &str) -> Result<Option<String>, Error> {
+        let mut numbers = self.milestone_numbers.lock().unwrap();
+        if numbers.is_none() {
+            let mut map = HashMap::new();
+            for milestone in self.request(milestones::Query::new(&self.owner, &self.repository),
+                                                  MemoryPageHandler::default()) {
+                let milestone = milestone?;
+                map.insert(milestone.title, milestone.number);
+            }
+            *numbers = Some(map);
+        }
+        Ok(numbers.as_ref().and_then(|numbers| numbers.get(title)).map(|number| milestone_name(*number)))
+    }
+
+    /// Fetches a single issue or pull request
+    ///
+    /// Issues and pull requests share numbers, so unless only one kind is imported,
+    /// not finding the number among either of them is expected.
+    /// It's fetched only once, even though it's both counted and listed.
+    fn item<'a>(&'a self, kind: Kind, number: usize, filter: &Filter) -> Box<Iterator<Item = Result<Item, Error>> + 'a> {
+        if let Some(items) = self.fetched.lock().unwrap().get(&(kind, number)) {
+            return Box::new(items.clone().into_iter().map(Ok));
+        }
+        let items: Box<Iterator<Item = Result<Item, Error>> + 'a> = match kind {
+            Kind::Issue =>
+                Box::new(self.request(issues::ItemQuery::new(&self.owner, &self.repository, number), MemoryPageHandler::default())
+                         .map(move |issue| issue.and_then(|issue| self.issue(issue)))),
+            Kind::MergeRequest =>
+                Box::new(self.request(pull_requests::ItemQuery::new(&self.owner, &self.repository, number), MemoryPageHandler::default())
+                         .map(move |pr| pr.and_then(|pr| self.pull_request(pr)))),
+        };
+        let optional = filter.kind.is_none();
+        let items = items.filter(|item| match item {
+            &Err(ref err) => !(optional && not_found(err)),
+            &Ok(_) => true,
+        }).collect::<Result<Vec<_>, _>>();
+        match items {
+            Ok(items) => {
+                // Not finding it may be an error once the kind is known
+                if !items.is_empty() {
+                    self.fetched.lock().unwrap().insert((kind, number), items.clone());
+                }
+                Box::new(items.into_iter().map(Ok))
+            },
+            Err(err) => Box::new(Some(Err(err)).into_iter()),
+        }
+    }
+
+    /// Converts timeline entries into events, each of them tagged like the entry it's made of
+    fn timeline_events<'a, I, T>(&'a self, url: String, items: I) -> Box<Iterator<Item = Result<(Event, T), Error>> + 'a>
+        where I: Iterator<Item = Result<(timeline::TimelineItem, T), Error>> + 'a, T: Clone + 'a {
+        Box::new(items.flat_map(move |item| match item.and_then(|(item, tag)| self.events(&url, item).map(|events| (events, tag))) {
+            Ok((events, tag)) => events.into_iter().map(|event| Ok((event, tag.clone()))).collect(),
+            Err(err) => vec![Err(err)],
+        }))
+    }
+
+    fn events(&self, url: &str, item: timeline::TimelineItem) -> Result<Vec<Event>, Error> {
+        use timeline::{TimelineItem, ReferenceSource, Closer};
+        match item {
+            TimelineItem::PullRequestReview { id, url, state, body, created_at, author, mut comments } => {
+                self.complete(Some(&mut comments), connection::Query::review_comments(&id))?;
+                let mut events = vec![Event::Reviewed { url, created_at, author: author.as_ref().map(Into::into), state, body }];
+                events.extend(comments.nodes.into_iter().map(review_commented));
+                Ok(events)
+            },
+            TimelineItem::PullRequestReviewThread { id, mut comments } => {
+                self.complete(Some(&mut comments), connection::Query::thread_comments(&id))?;
+                Ok(comments.nodes.into_iter().map(review_commented).collect())
+            },
+            TimelineItem::MilestonedEvent { id, database_id, created_at, actor, milestone_title } =>
+                Ok(vec![Event::Milestoned { url: event_url(url, database_id, &id), created_at, actor: actor.as_ref().map(Into::into),
+                                            milestone: self.milestone(&milestone_title)?, title: milestone_title }]),
+            TimelineItem::DemilestonedEvent { id, database_id, created_at, actor, milestone_title } =>
+                Ok(vec![Event::Demilestoned { url: event_url(url, database_id, &id), created_at, actor: actor.as_ref().map(Into::into),
+                                              milestone: self.milestone(&milestone_title)?, title: milestone_title }]),
+            TimelineItem::CrossReferencedEvent { id, database_id, created_at, actor, source } => {
+                let (kind, number, target, repository) = match source {
+                    ReferenceSource::Issue { number, url, repository } => (Kind::Issue, number, url, repository),
+                    ReferenceSource::PullRequest { number, url, repository } => (Kind::MergeRequest, number, url, repository),
+                };
+                let (item, text) = self.reference(kind, number, &repository);
+                Ok(vec![Event::Linked { url: event_url(url, database_id, &id), created_at, actor: actor.as_ref().map(Into::into),
+                                        relation: "cross-referenced".into(), item, target, commit: None, text }])
+            },
+            TimelineItem::ReferencedEvent { id, database_id, created_at, actor, commit: Some(commit), commit_repository } => {
+                let text = if self.local(&commit_repository) {
+                    None
+                } else {
+                    Some(format!("{}@{}", commit_repository.name_with_owner, commit.oid))
+                };
+                Ok(vec![Event::Linked { url: event_url(url, database_id, &id), created_at, actor: actor.as_ref().map(Into::into),
+                                        relation: "referenced".into(), item: None,
+                                        target: format!("{}/{}/commit/{}", self.web, commit_repository.name_with_owner, commit.oid),
+                                        commit: Some(commit.oid), text }])
+            },
+            TimelineItem::ClosedEvent { created_at, actor, closer } => {
+                let actor: Option<String> = actor.as_ref().map(Into::into);
+                match closer {
+                    Some(Closer::PullRequest { number, url: target, repository }) => {
+                        let (item, text) = self.reference(Kind::MergeRequest, number, &repository);
+                        let closer = provider::Closer::MergeRequest { url: target.clone(), item: item.clone() };
+                        Ok(vec![Event::Closed { created_at: created_at.clone(), actor: actor.clone(), closer: Some(closer) },
+                                // Only one close happens at a time, so it's told apart by the time
+                                Event::Linked { url: url.into(), created_at, actor, relation: "closed-by".into(), item, target,
+                                                commit: None, text }])
+                    },
+                    Some(Closer::Commit { oid }) =>
+                        Ok(vec![Event::Closed { created_at, actor, closer: Some(provider::Closer::Commit(oid)) }]),
+                    None => Ok(vec![Event::Closed { created_at, actor, closer: None }]),
+                }
+            },
+            TimelineItem::IssueComment { id, created_at, body, author, url, mut user_content_edits, mut reactions, .. } => {
+                self.complete(user_content_edits.as_mut(), connection::Query::edits(&id))?;
+                self.complete(reactions.as_mut(), connection::Query::reactions(&id))?;
+                let (body, edits) = edits::history(body, user_content_edits);
+                let reactions = reactions::reactions(&url, reactions);
+                Ok(vec![Event::Commented { url, body, created_at, author: author.as_ref().map(Into::into), edits, reactions }])
+            },
+            item => Ok(vec![event(url, item)]),
+        }
+    }
+
+    /// Returns `true` if the repository is the one being imported
+    fn local(&self, repository: &timeline::RepositoryRef) -> bool {
+        repository.name_with_owner.to_lowercase() == self.path.to_lowercase()
+    }
+
+    /// Refers to an issue or a pull request by its SIT item if it's in the same repository,
+    /// or describes it as `OWNER/REPO#N` otherwise
+    fn reference(&self, kind: Kind, number: usize, repository: &timeline::RepositoryRef) -> (Option<String>, Option<String>) {
+        if self.local(repository) {
+            (Some(item_name(kind, number)), None)
+        } else {
+            (None, Some(format!("{}#{}", repository.name_with_owner, number)))
+        }
+    }
+
+}
+
+/// Returns `true` if a request failed only because what it asked for doesn't exist
+fn not_found(err: &Error) -> bool {
+    match err {
+        &Error::Request { ref error, .. } => not_found(error),
+        &Error::GraphQL(ref errors) =>
+            !errors.is_empty() && errors.iter().all(|e| e.kind.as_ref().map(|kind| kind == "NOT_FOUND").unwrap_or(false)),
+        _ => false,
+    }
+}
+
+fn timeline_kind(kind: Kind) -> timeline::Kind {
+    match kind {
+        Kind::Issue => timeline::Kind::Issue,
+        Kind::MergeRequest => timeline::Kind::PullRequest,
+    }
+}
+
+/// Refers to an event of an issue or a pull request
+///
+/// Events are numbered the way GitHub's own links to them and migration archives do,
+/// so that an import from an archive can be continued from GitHub itself.
+fn event_url(url: &str, database_id: Option<u64>, id: &str) -> String {
+    match database_id {
+        Some(database_id) => format!("{}#event-{}", url, database_id),
+        None => format!("{}#event-{}", url, id),
+    }
+}
+
+fn review_commented(comment: timeline::ReviewComment) -> Event {
+    Event::ReviewCommented {
+        url: comment.url,
+        created_at: comment.created_at,
+        author: comment.author.as_ref().map(Into::into),
+        body: comment.body,
+        review: comment.pull_request_review.map(|r| r.url),
+        path: comment.path,
+        position: comment.original_position,
+        diff_hunk: comment.diff_hunk,
+        commit: comment.original_commit.map(|c| c.oid),
+        in_reply_to: comment.reply_to.map(|c| c.url),
+    }
+}
+
+fn event(url: &str, item: timeline::TimelineItem) -> Event {
+    use timeline::TimelineItem;
+    match item {
+        TimelineItem::AssignedEvent { id, database_id, created_at, actor, assignee: Some(assignee) } =>
+            Event::Assigned { url: event_url(url, database_id, &id), created_at, actor: actor.as_ref().map(Into::into), assignee: (&assignee).into() },
+        TimelineItem::UnassignedEvent { id, database_id, created_at, actor, assignee: Some(assignee) } =>
+            Event::Unassigned { url: event_url(url, database_id, &id), created_at, actor: actor.as_ref().map(Into::into), assignee: (&assignee).into() },
+        TimelineItem::RenamedTitleEvent { id, database_id, created_at, actor, previous_title, current_title } =>
+            Event::Renamed { url: event_url(url, database_id, &id), created_at, actor: actor.as_ref().map(Into::into),
+                             previous_title, current_title },
+        TimelineItem::LabeledEvent { id, database_id, created_at, actor, label } =>
+            Event::Labeled { url: event_url(url, database_id, &id), created_at, actor: actor.as_ref().map(Into::into), label },
+        TimelineItem::UnlabeledEvent { id, database_id, created_at, actor, label } =>
+            Event::Unlabeled { url: event_url(url, database_id, &id), created_at, actor: actor.as_ref().map(Into::into), label },
+        TimelineItem::ReopenedEvent { created_at, actor } =>
+            Event::Reopened { created_at, actor: actor.as_ref().map(Into::into) },
+        TimelineItem::LockedEvent { created_at, actor } =>
+            Event::Locked { created_at, actor: actor.as_ref().map(Into::into) },
+        TimelineItem::UnlockedEvent { created_at, actor } =>
+            Event::Unlocked { created_at, actor: actor.as_ref().map(Into::into) },
+        TimelineItem::MergedEvent { created_at, actor } =>
+            Event::Merged { created_at, actor: actor.as_ref().map(Into::into) },
+        TimelineItem::PullRequestReviewComment(comment) => review_commented(comment),
+        item => Event::Skipped { event: item.type_name().into() },
+    }
+}
+
+impl provider::Provider for GitHub {
+
+    fn open(source: &str, config: Option<&provider::Config>) -> Option<Result<Self, String>> {
+        let re = Regex::new("^https://([^/]+)/([^/]+)/([^/]+?)(\\.git)?(?:/(issues|pull)/(\\d+))?(/|#|$)").unwrap();
+        let captures = re.captures(source)?;
+        let host = &captures[1];
+        let github = config.and_then(|c| c.github.as_ref());
+        let host_config = github.and_then(|c| c.hosts.get(host));
+        // Other hosts are only recognized once configured, they might as well be GitLab instances
+        if host != GITHUB_HOST && host_config.is_none() {
+            return None;
+        }
+        let selection = match (captures.get(5).map(|m| m.as_str()), captures.get(6).map(|m| m.as_str().parse())) {
+            (Some("issues"), Some(Ok(number))) => Some((Kind::Issue, number)),
+            (Some("pull"), Some(Ok(number))) => Some((Kind::MergeRequest, number)),
+            (_, Some(Err(_))) => return Some(Err(format!("Invalid issue or pull request number in {}", source))),
+            _ => None,
+        };
+        let configured = host_config.and_then(|h| h.token.clone()).or_else(|| github.and_then(|c| c.token.clone()))
+            .map(|token| (token, config.and_then(|c| c.file.clone()).unwrap_or_default()));
+        let token = match token::github(host, configured) {
+            None => return Some(Err(format!("No token found for {}: set GITHUB_TOKEN, configure it with -c/--config \
+                                             or log in with a git credential helper or the GitHub CLI", host))),
+            Some((token, source)) => {
+                eprintln!("Using the {} token from {}", host, source);
+                token
+            },
+        };
+        let api = match host_config.and_then(|h| h.api.clone()) {
+            Some(api) => api,
+            None if host == GITHUB_HOST => GITHUB_GRAPHQL.into(),
+            None => format!("https://{}/api/graphql", host),
+        };
+        let owner = String::from(&captures[2]);
+        let repository = String::from(&captures[3]);
+        let path = format!("{}/{}", owner, repository);
+        let client = reqwest::Client::builder().build().unwrap();
+        Some(Ok(GitHub { api, selection, web: format!("https://{}", host), token, owner, repository, path, client, milestone_numbers: Mutex::new(None),
+                     fetched: Mutex::new(HashMap::new()), backoff: Backoff::default() }))
+    }
+
+    fn name(&self) -> &str {
+        "github"
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn selection(&self) -> Option<(Kind, usize)> {
+        self.selection
+    }
+
+    fn count(&self, kind: Kind, since: Option<&String>, filter: &Filter) -> Result<usize, Error> {
+        if let Some(number) = filter.number() {
+            return self.item(kind, number, filter).collect::<Result<Vec<_>, _>>().map(|items| items.len());
+        }
+        // The total count of a listing is only accurate if GitHub applies the whole filter,
+        // pull requests can't even be listed by update time
+        let listed = filter.created_after.is_some() || filter.created_before.is_some() || filter.updated_before.is_some() ||
+            filter.numbers != (None, None) ||
+            (kind == Kind::MergeRequest && (filter.author.is_some() || filter.since(since).is_some()));
+        if listed {
+            return provider::count(self.items(kind, since, filter, Box::new(MemoryPageHandler::default())), filter);
+        }
+        match kind {
+            Kind::Issue => {
+                let mut req = self.request(issues::Query::new(&self.owner, &self.repository).since(filter.since(since)).filter(filter),
+                                           MemoryPageHandler::default());
+                match req.next() {
+                    None => Ok(0),
+                    Some(Err(err)) => Err(err),
+                    Some(Ok(_)) => Ok(req.total_count().unwrap_or(0)),
+                }
+            },
+            Kind::MergeRequest => {
+                let mut req = self.request(pull_requests::Query::new(&self.owner, &self.repository)
+                                               .since(filter.since(since)).filter(filter),
+                                           MemoryPageHandler::default());
+                match req.next() {
+                    None => Ok(0),
+                    Some(Err(err)) => Err(err),
+                    Some(Ok(_)) => Ok(req.total_count().unwrap_or(0)),
+                }
+            },
+        }
+    }
+
+    fn items<'a>(&'a self, kind: Kind, since: Option<&String>, filter: &Filter, page_handler: Box<PageHandler>) -> Box<Iterator<Item = Result<Item, Error>> + 'a> {
+        if let Some(number) = filter.number() {
+            return self.item(kind, number, filter);
+        }
+        match kind {
+            Kind::Issue => {
+                Box::new(self.request(issues::Query::new(&self.owner, &self.repository).since(filter.since(since)).filter(filter),
+                                      page_handler)
+                         .map(move |issue| issue.and_then(|issue| self.issue(issue))))
+            },
+            Kind::MergeRequest => {
+                let since = filter.since(since);
+                let req = self.request(pull_requests::Query::new(&self.owner, &self.repository).since(since.clone()).filter(filter),
+                                       page_handler);
+                // When syncing, pull requests are listed most recently updated first
+                Box::new(req.take_while(move |pr| match (pr, since.as_ref()) {
+                                (&Ok(ref pr), Some(since)) => pr.updated_at.as_str() >= since.as_str(),
+                                _ => true,
+                            })
+                         .map(move |pr| pr.and_then(|pr| self.pull_request(pr))))
+            },
+        }
+    }
+
+    fn milestones<'a>(&'a self) -> Box<Iterator<Item = Result<Milestone, Error>> + 'a> {
+        Box::new(self.request(milestones::Query::new(&self.owner, &self.repository), MemoryPageHandler::default())
+                 .map(|milestone| milestone.map(Milestone::from)))
+    }
+
+    fn timeline<'a>(&'a self, item: &Item, page_handler: Box<PageHandler>) -> Box<Iterator<Item = Result<Event, Error>> + 'a> {
+        let query = timeline::Query::new(&self.owner, &self.repository, timeline_kind(item.kind), item.number);
+        Box::new(self.timeline_events(item.url.clone(), self.request(query, page_handler).map(|node| node.map(|node| (node, ()))))
+                 .map(|event| event.map(|(event, ())| event)))
+    }
+
+    fn timelines(&self, items: &[(&Item, Position)]) -> Vec<Timeline> {
+        // Timelines that were imported in part already are resumed one by one
+        let fresh: Vec<_> = items.iter().filter(|&&(_, ref position)| *position == Position::default())
+            .map(|&(item, _)| item.number).collect();
+        if fresh.len() < 2 {
+            return items.iter().map(|&(item, ref position)| Timeline::fetch(self, item, position.clone())).collect();
+        }
+        let kind = timeline_kind(items[0].0.kind);
+        // If the batch fails as a whole (for example, by exceeding the query limits),
+        // timelines are fetched one by one instead, which reports errors of each of them
+        let mut first_pages: HashMap<_, _> = self.request(timeline::Query::batch(&self.owner, &self.repository, kind, fresh),
+                                                          MemoryPageHandler::default())
+            .collect::<Result<_, _>>().unwrap_or_default();
+        items.iter().map(|&(item, ref position)| match first_pages.remove(&item.number) {
+            None => Timeline::fetch(self, item, position.clone()),
+            Some(page) => {
+                // Entries of the first page are resumed from as if it was fetched on its own
+                let first = page.nodes.into_iter().enumerate()
+                    .map(|(index, node)| Ok::<_, Error>((node, Position { index: index as u8 + 1, end_cursor: None })));
+                let mut timeline = Timeline::collect(self.timeline_events(item.url.clone(), first));
+                // Only timelines that don't fit into the first page are paged through
+                if let (None, PageInfo { has_next_page: true, end_cursor: Some(cursor) }) = (timeline.error.as_ref(), page.page_info) {
+                    let rest = Timeline::fetch(self, item, Position { index: 0, end_cursor: Some(cursor) });
+                    timeline.events.extend(rest.events);
+                    timeline.error = rest.error;
+                }
+                timeline
+            },
+        }).collect()
+    }
+
+    fn timeline_batch(&self) -> usize {
+        TIMELINE_BATCH
+    }
+
+    fn patch(&self, item: &Item) -> Result<Option<(&'static str, String)>, Error> {
+        let mut response = self.client.get(&format!("{}/{}/pull/{}.patch", self.web, self.path, item.number)).send()?;
+        let patch = response.text()?;
+        if response.status().is_success() {
+            Ok(Some(("git/pr.patch", patch)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn backoff(&self) -> Option<&Backoff> {
+        Some(&self.backoff)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json;
+    use provider::Provider;
+    use testing::{Server, Route};
+
+    fn config(json: &str) -> provider::Config {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn open() {
+        let config = config(r#"{"github": {"token": "token", "hosts": {
+            "ghe.example.com": {},
+            "api.example.com": {"token": "other", "api": "https://api.example.com/graphql"}
+        }}}"#);
+
+        let github = GitHub::open("https://github.com/sit-it/sit.git", Some(&config)).unwrap().unwrap();
+        assert_eq!((github.api.as_str(), github.web.as_str(), github.path()), (GITHUB_GRAPHQL, "https://github.com", "sit-it/sit"));
+
+        let github = GitHub::open("https://ghe.example.com/sit-it/sit", Some(&config)).unwrap().unwrap();
+        assert_eq!((github.api.as_str(), github.web.as_str(), github.token.as_str()),
+                   ("https://ghe.example.com/api/graphql", "https://ghe.example.com", "token"));
+
+        let github = GitHub::open("https://api.example.com/sit-it/sit", Some(&config)).unwrap().unwrap();
+        assert_eq!((github.api.as_str(), github.token.as_str()), ("https://api.example.com/graphql", "other"));
+
+        assert!(GitHub::open("https://gitlab.example.com/sit-it/sit", Some(&config)).is_none());
+
+        let github = GitHub::open("https://github.com/sit-it/sit/issues/12", Some(&config)).unwrap().unwrap();
+        assert_eq!((github.path(), github.selection()), ("sit-it/sit", Some((Kind::Issue, 12))));
+        let github = GitHub::open("https://github.com/sit-it/sit/pull/34/files", Some(&config)).unwrap().unwrap();
+        assert_eq!(github.selection(), Some((Kind::MergeRequest, 34)));
+        let github = GitHub::open("https://github.com/sit-it/sit/issues", Some(&config)).unwrap().unwrap();
+        assert_eq!(github.selection(), None);
+        assert!(GitHub::open("https://github.com/sit-it/sit/issues/99999999999999999999999", Some(&config)).unwrap().is_err());
+    }
+
+    fn linked(github: &GitHub, item: &str) -> (String, Option<String>, String, Option<String>, Option<String>) {
+        let item: timeline::TimelineItem = serde_json::from_str(item).unwrap();
+        match github.events("https://github.com/octo-org/octoroku/issues/1", item).unwrap().pop() {
+            Some(Event::Linked { relation, item, target, commit, text, .. }) => (relation, item, target, commit, text),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn links() {
+        let github = GitHub::new("http://localhost/graphql", "octo-org", "octoroku");
+
+        assert_eq!(linked(&github, r#"{"__typename": "CrossReferencedEvent", "id": "1", "actor": null, "createdAt": "2018-04-01T10:00:00Z",
+            "source": {"__typename": "PullRequest", "number": 4, "url": "https://github.com/octo-org/octoroku/pull/4",
+                       "repository": {"nameWithOwner": "Octo-Org/octoroku"}}}"#),
+                   ("cross-referenced".into(), Some("github-pr-4".into()), "https://github.com/octo-org/octoroku/pull/4".into(), None, None));
+
+        assert_eq!(linked(&github, r#"{"__typename": "CrossReferencedEvent", "id": "2", "actor": null, "createdAt": "2018-04-01T10:00:00Z",
+            "source": {"__typename": "Issue", "number": 7, "url": "https://github.com/octo-org/other/issues/7",
+                       "repository": {"nameWithOwner": "octo-org/other"}}}"#),
+                   ("cross-referenced".into(), None, "https://github.com/octo-org/other/issues/7".into(), None, Some("octo-org/other#7".into())));
+
+        assert_eq!(linked(&github, r#"{"__typename": "ReferencedEvent", "id": "3", "actor": null, "createdAt": "2018-04-01T10:00:00Z",
+            "commit": {"oid": "3f8c2a"}, "commitRepository": {"nameWithOwner": "octo-org/octoroku"}}"#),
+                   ("referenced".into(), None, "https://github.com/octo-org/octoroku/commit/3f8c2a".into(), Some("3f8c2a".into()), None));
+
+        assert_eq!(linked(&github, r#"{"__typename": "ClosedEvent", "actor": null, "createdAt": "2018-04-01T10:00:00Z",
+            "closer": {"__typename": "PullRequest", "number": 4, "url": "https://github.com/octo-org/octoroku/pull/4",
+                       "repository": {"nameWithOwner": "octo-org/octoroku"}}}"#),
+                   ("closed-by".into(), Some("github-pr-4".into()), "https://github.com/octo-org/octoroku/pull/4".into(), None, None));
+    }
+
+    #[test]
+    fn closer() {
+        let github = GitHub::new("http://localhost/graphql", "octo-org", "octoroku");
+        let closer = |closer: &str| {
+            let item = serde_json::from_str(&format!(r#"{{"__typename": "ClosedEvent", "actor": null, "createdAt": "2018-04-01T10:00:00Z",
+                                                         "closer": {}}}"#, closer)).unwrap();
+            match github.events("https://github.com/octo-org/octoroku/issues/1", item).unwrap().remove(0) {
+                Event::Closed { closer, .. } => closer,
+                event => panic!("unexpected event {:?}", event),
+            }
+        };
+        assert_eq!(closer(r#"{"__typename": "Commit", "oid": "3f8c2a"}"#), Some(provider::Closer::Commit("3f8c2a".into())));
+        assert_eq!(closer(r#"{"__typename": "PullRequest", "number": 4, "url": "https://github.com/octo-org/other/pull/4",
+                              "repository": {"nameWithOwner": "octo-org/other"}}"#),
+                   Some(provider::Closer::MergeRequest { url: "https://github.com/octo-org/other/pull/4".into(), item: None }));
+        assert_eq!(closer(r#"{"__typename": "PullRequest", "number": 4, "url": "https://github.com/octo-org/octoroku/pull/4",
+                              "repository": {"nameWithOwner": "octo-org/octoroku"}}"#),
+                   Some(provider::Closer::MergeRequest { url: "https://github.com/octo-org/octoroku/pull/4".into(),
+                                                         item: Some("github-pr-4".into()) }));
+        assert_eq!(closer("null"), None);
+    }
+
+    fn comment(github: &GitHub, item: &str) -> (String, Vec<provider::Edit>, Vec<provider::Reaction>) {
+        let item: timeline::TimelineItem = serde_json::from_str(item).unwrap();
+        match github.events("https://github.com/octo-org/octoroku/issues/1", item).unwrap().remove(0) {
+            Event::Commented { body, edits, reactions, .. } => (body, edits, reactions),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn complete_edits() {
+        let server = Server::start(vec![Route::new(vec![r#"node(id: "MDEyOklzc3VlQ29tbWVudDEwMA")"#, "userContentEdits(", r#"after: "Y3Vyc29yOjE=""#],
+                                                   include_str!("../fixtures/github/comment_edits.json"))]);
+        let github = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        let (body, edits, _) = comment(&github, r#"{"__typename": "IssueComment", "id": "MDEyOklzc3VlQ29tbWVudDEwMA",
+            "url": "https://github.com/octo-org/octoroku/issues/1#issuecomment-100", "body": "On it.",
+            "createdAt": "2018-04-01T11:00:00Z", "updatedAt": "2018-04-01T11:10:00Z", "author": null,
+            "userContentEdits": {"pageInfo": {"hasNextPage": true, "endCursor": "Y3Vyc29yOjE="}, "nodes": [
+                {"editedAt": "2018-04-01T11:10:00Z", "diff": "On it.", "editor": null}
+            ]}}"#);
+        assert_eq!(body, "On it!");
+        assert_eq!(edits.iter().map(|e| e.text.as_str()).collect::<Vec<_>>(), vec!["On it", "On it."]);
+        assert_eq!(server.queries().len(), 1);
+    }
+
+    #[test]
+    fn complete_reactions() {
+        let server = Server::start(vec![Route::new(vec![r#"node(id: "MDEyOklzc3VlQ29tbWVudDEwMA")"#, "reactions(", r#"after: "Y3Vyc29yOjE=""#],
+                                                   include_str!("../fixtures/github/comment_reactions.json"))]);
+        let github = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        let (_, _, reactions) = comment(&github, r#"{"__typename": "IssueComment", "id": "MDEyOklzc3VlQ29tbWVudDEwMA",
+            "url": "https://github.com/octo-org/octoroku/issues/1#issuecomment-100", "body": "On it",
+            "createdAt": "2018-04-01T11:00:00Z", "updatedAt": "2018-04-01T11:00:00Z", "author": null,
+            "reactions": {"pageInfo": {"hasNextPage": true, "endCursor": "Y3Vyc29yOjE="}, "nodes": [
+                {"id": "MDg6UmVhY3Rpb24z", "content": "HEART", "createdAt": "2018-04-01T11:10:00Z", "user": null}
+            ]}}"#);
+        assert_eq!(reactions.iter().map(|r| r.content.as_str()).collect::<Vec<_>>(), vec!["HEART", "THUMBS_UP", "HEART"]);
+        assert_eq!(reactions[2].url, "https://github.com/octo-org/octoroku/issues/1#issuecomment-100#reaction-MDg6UmVhY3Rpb242");
+        assert_eq!(server.queries().len(), 1);
+    }
+
+    fn review_comment(url: &str, created_at: &str, reply_to: &str) -> String {
+        format!(r#"{{"url": "{}", "body": "Here", "createdAt": "{}", "path": "src/main.rs", "originalPosition": 4,
+                     "diffHunk": "@@ -1,2 +1,3 @@", "originalCommit": {{"oid": "3f8c2a"}}, "replyTo": {},
+                     "pullRequestReview": {{"url": "https://github.com/octo-org/octoroku/pull/4#pullrequestreview-20"}}, "author": null}}"#,
+                url, created_at, reply_to)
+    }
+
+    fn review_commented(event: &Event) -> (&str, Option<&str>, Option<&str>) {
+        match event {
+            &Event::ReviewCommented { ref url, ref review, ref in_reply_to, .. } =>
+                (url.as_str(), review.as_ref().map(String::as_str), in_reply_to.as_ref().map(String::as_str)),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn review_comments() {
+        let server = Server::start(vec![Route::new(vec![r#"node(id: "MDE3OlB1bGxSZXF1ZXN0UmV2aWV3MjA")"#, "... on PullRequestReview ",
+                                                        "comments(", r#"after: "Y3Vyc29yOjE=""#],
+                                                   include_str!("../fixtures/github/review_comments.json"))]);
+        let github = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        let url = "https://github.com/octo-org/octoroku/pull/4";
+        let review = "https://github.com/octo-org/octoroku/pull/4#pullrequestreview-20";
+
+        let item = serde_json::from_str(&format!(r#"{{"__typename": "PullRequestReview", "id": "MDE3OlB1bGxSZXF1ZXN0UmV2aWV3MjA",
+            "url": "{}", "state": "COMMENTED", "body": "", "createdAt": "2018-04-03T10:00:00Z", "author": null,
+            "comments": {{"pageInfo": {{"hasNextPage": true, "endCursor": "Y3Vyc29yOjE="}}, "nodes": [{}]}}}}"#,
+            review, review_comment("https://github.com/octo-org/octoroku/pull/4#discussion_r200", "2018-04-03T10:00:00Z", "null"))).unwrap();
+        let events = github.events(url, item).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(review_commented(&events[1]), ("https://github.com/octo-org/octoroku/pull/4#discussion_r200", Some(review), None));
+        assert_eq!(review_commented(&events[2]), ("https://github.com/octo-org/octoroku/pull/4#discussion_r201", Some(review), None));
+        assert_eq!(server.queries().len(), 1);
+
+        let item = serde_json::from_str(&format!(r#"{{"__typename": "PullRequestReviewThread", "id": "MDIzOlB1bGxSZXF1ZXN0UmV2aWV3VGhyZWFkMQ",
+            "comments": {{"pageInfo": {{"hasNextPage": false, "endCursor": null}}, "nodes": [{}, {}]}}}}"#,
+            review_comment("https://github.com/octo-org/octoroku/pull/4#discussion_r200", "2018-04-03T10:00:00Z", "null"),
+            review_comment("https://github.com/octo-org/octoroku/pull/4#discussion_r202", "2018-04-04T10:00:00Z",
+                           r#"{"url": "https://github.com/octo-org/octoroku/pull/4#discussion_r200"}"#))).unwrap();
+        let events = github.events(url, item).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(review_commented(&events[1]), ("https://github.com/octo-org/octoroku/pull/4#discussion_r202", Some(review),
+                                                  Some("https://github.com/octo-org/octoroku/pull/4#discussion_r200")));
+
+        let item = serde_json::from_str(&format!(r#"{{"__typename": "PullRequestReviewComment", {}"#,
+            &review_comment("https://github.com/octo-org/octoroku/pull/4#discussion_r203", "2018-04-05T10:00:00Z", "null")[1..])).unwrap();
+        let events = github.events(url, item).unwrap();
+        assert_eq!(review_commented(&events[0]), ("https://github.com/octo-org/octoroku/pull/4#discussion_r203", Some(review), None));
+        assert_eq!(server.queries().len(), 1);
+    }
+
+}
@@ -0,0 +1,562 @@
+use std::collections::VecDeque;
+use std::str::{self, FromStr};
+use serde::Deserialize;
+use reqwest::{self, StatusCode};
+
+use serde_json;
+
+use graphql::{PageHandler, MemoryPageHandler};
+use error::Error;
+use filter::Filter;
+use provider::{self, Kind, Item, Event, Label};
+
+const GITLAB_URL : &str = "https://gitlab.com";
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub token: String,
+    /// Base URL of a self-hosted instance
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct User {
+    pub username: String,
+    pub name: Option<String>,
+    pub web_url: String,
+    pub public_email: Option<String>,
+}
+
+impl<'a> Into<String> for &'a User {
+
+    fn into(self) -> String {
+        let name: String = self.name.clone().unwrap_or_default();
+        let url: String = format!("({})", self.web_url);
+        let email: String = self.public_email.as_ref().map(|s| if s == "" { s.clone() } else { format!("<{}>", s) }).unwrap_or_default();
+
+        let elements: Vec<_> = vec![name, url, email].into_iter().filter(|s| !s.is_empty())
+            .collect();
+        elements.join(" ")
+    }
+
+}
+
+use files::Files;
+use std::io::{Read, Cursor};
+impl<'a> Into<Files<&'a str, Box<Read>>> for &'a User {
+
+    fn into(self) -> Files<&'a str, Box<Read>> {
+        let s: String = self.into();
+        Files(vec![(".authors", Box::new(Cursor::new(s.into_bytes())))])
+    }
+
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Issue {
+    pub iid: usize,
+    pub web_url: String,
+    pub state: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub closed_at: Option<String>,
+    pub author: Option<User>,
+    pub assignees: Vec<User>,
+    pub labels: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MergeRequest {
+    pub iid: usize,
+    pub web_url: String,
+    pub state: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub merged_at: Option<String>,
+    pub author: Option<User>,
+    pub assignees: Vec<User>,
+    pub labels: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Note {
+    pub id: usize,
+    pub body: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub system: bool,
+    pub author: Option<User>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StateEvent {
+    pub id: usize,
+    pub user: Option<User>,
+    pub created_at: String,
+    pub state: String,
+}
+
+fn kind_path(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Issue => "issues",
+        Kind::MergeRequest => "merge_requests",
+    }
+}
+
+/// Query parameters of a request, encoded once it's sent
+pub type Params = Vec<(&'static str, String)>;
+
+/// GitLab project, as identified by its API endpoint and full path
+pub struct Project {
+    pub api: String,
+    pub path: String,
+}
+
+impl Project {
+
+    fn url<S: AsRef<str>>(&self, suffix: S) -> String {
+        format!("{}/projects/{}/{}", self.api.trim_right_matches('/'), self.path.replace("/", "%2F"), suffix.as_ref())
+    }
+
+    /// Lists issues or merge requests updated since a given time
+    ///
+    /// Labels are left to be filtered out by the caller, GitLab only supports
+    /// requiring all of them.
+    pub fn items(&self, kind: Kind, since: Option<&String>, filter: &Filter) -> (String, Params) {
+        let mut params: Params = vec![("order_by", "created_at".into()), ("sort", "asc".into())];
+        if let Some(updated_after) = filter.since(since) {
+            params.push(("updated_after", updated_after));
+        }
+        match (kind, filter.closed) {
+            (_, Some(false)) => params.push(("state", "opened".into())),
+            // Merged merge requests are not in the `closed` state
+            (Kind::Issue, Some(true)) => params.push(("state", "closed".into())),
+            _ => (),
+        }
+        for &(param, value) in [("created_after", &filter.created_after), ("created_before", &filter.created_before),
+                                ("updated_before", &filter.updated_before), ("author_username", &filter.author)].iter() {
+            if let Some(ref value) = *value {
+                params.push((param, value.clone()));
+            }
+        }
+        (self.url(kind_path(kind)), params)
+    }
+
+    pub fn notes(&self, kind: Kind, iid: usize) -> String {
+        self.url(format!("{}/{}/notes?order_by=created_at&sort=asc", kind_path(kind), iid))
+    }
+
+    pub fn state_events(&self, kind: Kind, iid: usize) -> String {
+        self.url(format!("{}/{}/resource_state_events", kind_path(kind), iid))
+    }
+
+}
+
+/// GitLab REST API request that iterates through all pages of a list
+///
+/// The page number is kept as `end_cursor` of the page handler's position.
+pub struct PagedRequest<T, P> where P: PageHandler {
+    url: String,
+    params: Params,
+    token: String,
+    queued: VecDeque<T>,
+    next_page: Option<String>,
+    client: reqwest::Client,
+    page_handler: P,
+    total_count: Option<usize>,
+}
+
+impl<T, P> PagedRequest<T, P> where P: PageHandler {
+
+    /// Creates a request that starts from the page handler's current position
+    ///
+    /// Items up to the position are skipped, so it should only be saved once
+    /// the item handed out last is processed completely.
+    pub fn new<S: Into<String>>(client: reqwest::Client, url: S, token: S, page_handler: P) -> Self {
+        let next_page = Some(page_handler.page_position().end_cursor.unwrap_or_else(|| "1".into()));
+        PagedRequest { url: url.into(), params: vec![], token: token.into(), queued: VecDeque::new(), next_page, client, page_handler, total_count: None }
+    }
+
+    /// Adds query parameters to those of the URL
+    pub fn params(self, params: Params) -> Self {
+        PagedRequest { params, ..self }
+    }
+
+    pub fn total_count(&self) -> Option<usize> {
+        self.total_count
+    }
+
+}
+
+fn header<T: FromStr>(resp: &reqwest::Response, name: &str) -> Option<T> {
+    resp.headers().get_raw(name)
+        .and_then(|v| v.one())
+        .and_then(|v| str::from_utf8(v).ok())
+        .and_then(|v| v.parse().ok())
+}
+
+impl<T, P> PagedRequest<T, P> where P: PageHandler, for<'de> T: Deserialize<'de> {
+
+    fn fetch(&mut self, page: &str) -> Result<Vec<T>, Error> {
+        let mut resp = self.client.get(&self.url)
+            .header(reqwest::header::Authorization(reqwest::header::Bearer { token: self.token.clone() }))
+            .query(&self.params)
+            .query(&[("per_page", "100"), ("page", page)])
+            .send()?;
+        let body = resp.text()?;
+        if !resp.status().is_success() {
+            return Err(Error::Status { status: resp.status(), body });
+        }
+        // GitLab omits `X-Total` for very large lists
+        self.total_count = header(&resp, "X-Total").or(self.total_count);
+        self.next_page = header::<String>(&resp, "X-Next-Page").and_then(|p| if p == "" { None } else { Some(p) });
+        Ok(serde_json::from_str(&body)?)
+    }
+
+}
+
+impl<T, P> Iterator for PagedRequest<T, P> where P: PageHandler, for<'de> T: Deserialize<'de> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut pos = self.page_handler.page_position();
+        match self.queued.pop_front() {
+            Some(item) => {
+                pos.index += 1;
+                self.page_handler.set_page_position(&pos);
+                Some(Ok(item))
+            },
+            None => {
+                let page = match self.next_page.take() {
+                    None => return None,
+                    Some(page) => page,
+                };
+                if pos.end_cursor.as_ref() != Some(&page) {
+                    pos.index = 0;
+                    pos.end_cursor = Some(page.clone());
+                }
+                self.page_handler.set_page_position(&pos);
+                let result = match self.fetch(&page) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        // Stop here, the position is kept so that the page can be retried
+                        self.next_page = None;
+                        return Some(Err(Error::request(format!("{} (page {})", self.url, page), err)));
+                    },
+                };
+                let mut iter = result.into_iter().skip(pos.index as usize);
+
+                match iter.next() {
+                    // Everything on a page resumed from was processed already
+                    None if pos.index > 0 && self.next_page.is_some() => self.next(),
+                    None => None,
+                    Some(item) => {
+                        self.queued.extend(iter);
+                        pos.index += 1;
+                        self.page_handler.set_page_position(&pos);
+                        Some(Ok(item))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An entry in the history of an issue or a merge request
+pub enum TimelineItem {
+    Note(Note),
+    StateEvent(StateEvent),
+}
+
+impl TimelineItem {
+    pub fn created_at(&self) -> &str {
+        match self {
+            &TimelineItem::Note(ref note) => &note.created_at,
+            &TimelineItem::StateEvent(ref event) => &event.created_at,
+        }
+    }
+}
+
+/// Fetches notes and state events of an issue or a merge request, in chronological order
+///
+/// System notes are skipped as they are generated by GitLab and duplicate
+/// information that is imported otherwise.
+pub fn timeline<P1, P2>(notes: PagedRequest<Note, P1>, state_events: PagedRequest<StateEvent, P2>) -> Result<Vec<TimelineItem>, Error>
+    where P1: PageHandler, P2: PageHandler {
+    let mut items = vec![];
+    for note in notes {
+        items.push(TimelineItem::Note(note?));
+    }
+    for event in state_events {
+        items.push(TimelineItem::StateEvent(event?));
+    }
+    items.sort_by(|a, b| a.created_at().cmp(b.created_at()));
+    Ok(items)
+}
+
+pub struct GitLab {
+    token: String,
+    project: Project,
+    client: reqwest::Client,
+}
+
+impl GitLab {
+
+    fn count<T>(&self, (url, params): (String, Params)) -> Result<usize, Error> where for<'de> T: Deserialize<'de> {
+        let mut req = PagedRequest::<T, _>::new(self.client.clone(), url, self.token.clone(), MemoryPageHandler::default())
+            .params(params);
+        match req.next() {
+            None => Ok(0),
+            Some(Err(err)) => Err(err),
+            Some(Ok(_)) => Ok(req.total_count().unwrap_or(0)),
+        }
+    }
+
+}
+
+fn labels(names: Vec<String>) -> Vec<Label> {
+    names.into_iter().map(|name| Label { name, color: None }).collect()
+}
+
+impl From<Issue> for Item {
+    fn from(issue: Issue) -> Self {
+        Item {
+            kind: Kind::Issue,
+            name: format!("gitlab-issue-{}", issue.iid),
+            number: issue.iid,
+            author: issue.author.as_ref().map(Into::into),
+            author_login: issue.author.as_ref().map(|a| a.username.clone()),
+            closed: issue.state != "opened",
+            url: issue.web_url,
+            title: issue.title,
+            body: issue.description.unwrap_or_default(),
+            edits: vec![],
+            reactions: vec![],
+            created_at: issue.created_at,
+            updated_at: issue.updated_at,
+            labels: Some(labels(issue.labels)),
+            assignees: Some(issue.assignees.iter().map(Into::into).collect()),
+            milestone: None,
+        }
+    }
+}
+
+impl From<MergeRequest> for Item {
+    fn from(mr: MergeRequest) -> Self {
+        Item {
+            kind: Kind::MergeRequest,
+            name: format!("gitlab-mr-{}", mr.iid),
+            number: mr.iid,
+            author: mr.author.as_ref().map(Into::into),
+            author_login: mr.author.as_ref().map(|a| a.username.clone()),
+            closed: mr.state != "opened",
+            url: mr.web_url,
+            title: mr.title,
+            body: mr.description.unwrap_or_default(),
+            edits: vec![],
+            reactions: vec![],
+            created_at: mr.created_at,
+            updated_at: mr.updated_at,
+            labels: Some(labels(mr.labels)),
+            assignees: Some(mr.assignees.iter().map(Into::into).collect()),
+            milestone: None,
+        }
+    }
+}
+
+fn event(url: &str, item: TimelineItem) -> Event {
+    match item {
+        TimelineItem::Note(ref note) if note.system => Event::Skipped { event: "system note".into() },
+        TimelineItem::Note(note) =>
+            Event::Commented { url: format!("{}#note_{}", url, note.id), author: note.author.as_ref().map(Into::into),
+                               body: note.body, created_at: note.created_at,
+                               edits: vec![], reactions: vec![] },
+        TimelineItem::StateEvent(event) => {
+            let actor = event.user.as_ref().map(Into::into);
+            let created_at = event.created_at;
+            match event.state.as_str() {
+                "closed" => Event::Closed { created_at, actor, closer: None },
+                "reopened" => Event::Reopened { created_at, actor },
+                "merged" => Event::Merged { created_at, actor },
+                state => Event::Skipped { event: format!("{} state event", state) },
+            }
+        },
+    }
+}
+
+impl provider::Provider for GitLab {
+
+    fn open(source: &str, config: Option<&provider::Config>) -> Option<Result<Self, String>> {
+        let gitlab = config.and_then(|c| c.gitlab.as_ref());
+        let url = gitlab.and_then(|c| c.url.clone()).unwrap_or_else(|| GITLAB_URL.into());
+        let url = url.trim_right_matches('/');
+        if !source.starts_with(&format!("{}/", url)) {
+            return None;
+        }
+        let token = match gitlab {
+            None => return Some(Err("-c/--config with gitlab provider configuration required for GitLab to configure the token".into())),
+            Some(gitlab) => gitlab.token.clone(),
+        };
+        let path = source[url.len() + 1..].trim_right_matches('/').trim_right_matches(".git");
+        if !path.contains('/') {
+            return Some(Err("Invalid source URL".into()));
+        }
+        let project = Project { api: format!("{}/api/v4", url), path: path.into() };
+        let client = reqwest::Client::builder().build().unwrap();
+        Some(Ok(GitLab { token, project, client }))
+    }
+
+    fn name(&self) -> &str {
+        "gitlab"
+    }
+
+    fn path(&self) -> &str {
+        &self.project.path
+    }
+
+    fn count(&self, kind: Kind, since: Option<&String>, filter: &Filter) -> Result<usize, Error> {
+        // The total count of a listing is only accurate if GitLab applies the whole filter
+        if !filter.labels.is_empty() || filter.numbers != (None, None) || (kind == Kind::MergeRequest && filter.closed == Some(true)) {
+            return provider::count(self.items(kind, since, filter, Box::new(MemoryPageHandler::default())), filter);
+        }
+        match kind {
+            Kind::Issue => self.count::<Issue>(self.project.items(kind, since, filter)),
+            Kind::MergeRequest => self.count::<MergeRequest>(self.project.items(kind, since, filter)),
+        }
+    }
+
+    fn items<'a>(&'a self, kind: Kind, since: Option<&String>, filter: &Filter, page_handler: Box<PageHandler>) -> Box<Iterator<Item = Result<Item, Error>> + 'a> {
+        let (url, params) = self.project.items(kind, since, filter);
+        match kind {
+            Kind::Issue =>
+                Box::new(PagedRequest::<Issue, _>::new(self.client.clone(), url, self.token.clone(), page_handler).params(params)
+                         .map(|issue| issue.map(Item::from))),
+            Kind::MergeRequest =>
+                Box::new(PagedRequest::<MergeRequest, _>::new(self.client.clone(), url, self.token.clone(), page_handler).params(params)
+                         .map(|mr| mr.map(Item::from))),
+        }
+    }
+
+    /// Notes and state events are fetched in full and merged, so the page handler is not used
+    fn timeline<'a>(&'a self, item: &Item, _page_handler: Box<PageHandler>) -> Box<Iterator<Item = Result<Event, Error>> + 'a> {
+        let notes = PagedRequest::new(self.client.clone(), self.project.notes(item.kind, item.number), self.token.clone(),
+                                      MemoryPageHandler::default());
+        let state_events = PagedRequest::new(self.client.clone(), self.project.state_events(item.kind, item.number), self.token.clone(),
+                                             MemoryPageHandler::default());
+        match timeline(notes, state_events) {
+            Ok(items) => {
+                let url = item.url.clone();
+                Box::new(items.into_iter().map(move |item| Ok(event(&url, item))))
+            },
+            Err(err) => Box::new(::std::iter::once(Err(err))),
+        }
+    }
+
+    fn patch(&self, item: &Item) -> Result<Option<(&'static str, String)>, Error> {
+        let mut response = self.client.get(&self.project.url(format!("merge_requests/{}/raw_diffs", item.number)))
+            .header(reqwest::header::Authorization(reqwest::header::Bearer { token: self.token.clone() }))
+            .send()?;
+        match response.status() {
+            status if status.is_success() => Ok(Some(("git/mr.patch", response.text()?))),
+            // Not being allowed to read it is more likely a problem with the token than with the merge request
+            status @ StatusCode::Unauthorized | status @ StatusCode::Forbidden =>
+                Err(Error::Status { status, body: response.text()? }),
+            _ => Ok(None),
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use provider::Provider;
+    use testing::{Server, Route};
+
+    fn gitlab(server: &Server) -> GitLab {
+        GitLab { token: "token".into(), client: reqwest::Client::new(),
+                 project: Project { api: server.url.replace("/graphql", "/api/v4"), path: "group/project".into() } }
+    }
+
+    #[test]
+    fn items() {
+        let server = Server::start(vec![Route::new(vec!["/api/v4/projects/group%2Fproject/issues?", "&page=2"],
+                                                   include_str!("../fixtures/gitlab/issues_2.json"))
+                                            .header("X-Total", "3"),
+                                        Route::new(vec!["/api/v4/projects/group%2Fproject/issues?"],
+                                                   include_str!("../fixtures/gitlab/issues_1.json"))
+                                            .header("X-Total", "3").header("X-Next-Page", "2")]);
+        let gitlab = gitlab(&server);
+        let filter = Filter { author: Some("hu&bot".into()), created_after: Some("2018-04-01T00:00:00Z".into()), ..Filter::default() };
+        let items: Vec<_> = gitlab.items(Kind::Issue, None, &filter, Box::new(MemoryPageHandler::default()))
+            .map(|item| item.unwrap()).collect();
+        assert_eq!(items.iter().map(|item| item.name.as_str()).collect::<Vec<_>>(), vec!["gitlab-issue-1", "gitlab-issue-2", "gitlab-issue-3"]);
+        assert!(items[0].closed && !items[1].closed);
+        assert_eq!(items[0].author_login, Some("hubot".into()));
+        assert_eq!(items[0].assignees, Some(vec!["The Octocat (https://gitlab.com/octocat) <octocat@example.com>".into()]));
+        assert_eq!(items[1].body, "");
+        // Values are encoded rather than spliced into the query string
+        assert_eq!(server.count(&["order_by=created_at&sort=asc", "&author_username=hu%26bot&",
+                                  "&created_after=2018-04-01T00%3A00%3A00Z&", "per_page=100"]), 2);
+
+        assert_eq!(Provider::count(&gitlab, Kind::Issue, Some(&"2018-04-02T00:00:00Z".into()), &Filter::default()).unwrap(), 3);
+        assert_eq!(server.count(&["updated_after=2018-04-02T00%3A00%3A00Z", "&page=1"]), 1);
+    }
+
+    #[test]
+    fn timeline() {
+        let server = Server::start(vec![Route::new(vec!["/issues/1/notes?"], include_str!("../fixtures/gitlab/notes.json")),
+                                        Route::new(vec!["/issues/1/resource_state_events?"], include_str!("../fixtures/gitlab/state_events.json"))]);
+        let gitlab = gitlab(&server);
+        let issue = Issue { iid: 1, web_url: "https://gitlab.com/group/project/issues/1".into(), state: "closed".into(),
+                            title: "Fix the build".into(), description: None, created_at: "2018-04-01T10:00:00.000Z".into(),
+                            updated_at: "2018-04-02T10:00:00.000Z".into(), closed_at: None, author: None, assignees: vec![], labels: vec![] };
+        let events: Vec<_> = gitlab.timeline(&issue.into(), Box::new(MemoryPageHandler::default())).map(|event| event.unwrap()).collect();
+        assert_eq!(events.len(), 3);
+        match events[0] {
+            Event::Skipped { ref event } => assert_eq!(event, "system note"),
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        match events[1] {
+            Event::Commented { ref url, ref body, ref author, .. } => {
+                assert_eq!(url, "https://gitlab.com/group/project/issues/1#note_101");
+                assert_eq!(body, "On it");
+                assert_eq!(author.as_ref().map(String::as_str), Some("The Octocat (https://gitlab.com/octocat) <octocat@example.com>"));
+            },
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        match events[2] {
+            Event::Closed { ref created_at, ref closer, .. } => assert_eq!((created_at.as_str(), closer), ("2018-04-02T10:00:00.000Z", &None)),
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        assert_eq!(server.count(&["/api/v4/projects/group%2Fproject/issues/1/notes?order_by=created_at&sort=asc&per_page=100&page=1"]), 1);
+    }
+
+    #[test]
+    fn patch() {
+        let server = Server::start(vec![Route::new(vec!["/merge_requests/1/raw_diffs"], "diff --git a/README b/README\n"),
+                                        Route { status: 401, ..Route::new(vec!["/merge_requests/3/raw_diffs"], "{\"message\":\"401 Unauthorized\"}") }]);
+        let gitlab = gitlab(&server);
+        let issue = Issue { iid: 1, web_url: "https://gitlab.com/group/project/merge_requests/1".into(), state: "opened".into(),
+                            title: "Fix the build".into(), description: None, created_at: "2018-04-01T10:00:00.000Z".into(),
+                            updated_at: "2018-04-01T10:00:00.000Z".into(), closed_at: None, author: None, assignees: vec![], labels: vec![] };
+        let mut item: Item = issue.into();
+        assert_eq!(gitlab.patch(&item).unwrap(), Some(("git/mr.patch", "diff --git a/README b/README\n".into())));
+        assert_eq!(server.count(&["/api/v4/projects/group%2Fproject/merge_requests/1/raw_diffs"]), 1);
+        // A merge request without a diff is imported without it
+        item.number = 2;
+        assert_eq!(gitlab.patch(&item).unwrap(), None);
+        // But a token that isn't allowed to read it fails the import
+        item.number = 3;
+        match gitlab.patch(&item) {
+            Err(Error::Status { status, .. }) => assert_eq!(status, StatusCode::Unauthorized),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+}
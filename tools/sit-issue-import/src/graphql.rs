@@ -18,11 +18,11 @@ pub trait HasTotalCount {
     fn total_count(&self) -> usize;
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct PageInfo {
-    has_next_page: bool,
-    end_cursor: Option<String>,
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -42,7 +42,22 @@ impl<T> IntoIterator for Paged<T> {
     }
 }
 
+/// List of nodes that is not paged through
+#[derive(Deserialize, Debug)]
+pub struct Nodes<T> {
+    pub nodes: Vec<T>,
+}
+
+/// First page of a list nested in another node, the rest is fetched on its own
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection<T> {
+    pub page_info: PageInfo,
+    pub nodes: Vec<T>,
+}
+
 use reqwest;
+use reqwest::StatusCode;
 
 enum Maybe<T> {
     None,
@@ -59,10 +74,10 @@ impl<T> From<Option<T>> for Maybe<T> {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
 pub struct Position {
-    index: u8,
-    end_cursor: Option<String>,
+    pub index: u8,
+    pub end_cursor: Option<String>,
 }
 
 pub trait PageHandler {
@@ -70,6 +85,16 @@ pub trait PageHandler {
     fn set_page_position(&mut self, pos: &Position);
 }
 
+impl<P: PageHandler + ?Sized> PageHandler for Box<P> {
+    fn page_position(&self) -> Position {
+        (**self).page_position()
+    }
+
+    fn set_page_position(&mut self, pos: &Position) {
+        (**self).set_page_position(pos)
+    }
+}
+
 #[derive(Default)]
 pub struct MemoryPageHandler {
     position: Position,
@@ -85,14 +110,121 @@ impl PageHandler for MemoryPageHandler {
     }
 }
 
-use std::str::FromStr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::fs;
+use std::io;
+use serde_json;
+
+/// Page positions of multiple requests, persisted in a JSON file
+#[derive(Clone)]
+pub struct PositionFile {
+    path: PathBuf,
+    positions: HashMap<String, Position>,
+}
+
+impl PositionFile {
+
+    /// Starts with no positions, discarding the ones saved previously
+    pub fn create<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let file = PositionFile { path: path.into(), positions: HashMap::new() };
+        file.save()?;
+        Ok(file)
+    }
+
+    /// Loads previously saved positions (if there are any)
+    pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+        let positions = match fs::File::open(&path) {
+            Ok(file) => serde_json::from_reader(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(PositionFile { path, positions })
+    }
+
+    fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(&self.path)?;
+        serde_json::to_writer(file, &self.positions).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Position of a request, from the beginning if there's none saved
+    pub fn position<S: AsRef<str>>(&self, key: S) -> Position {
+        self.positions.get(key.as_ref()).cloned().unwrap_or_default()
+    }
+
+    pub fn set_position<S: Into<String>>(&mut self, key: S, position: Position) -> io::Result<()> {
+        self.positions.insert(key.into(), position);
+        self.save()
+    }
+
+    /// Forgets the position of a completed request
+    pub fn forget<S: AsRef<str>>(&mut self, key: S) -> io::Result<()> {
+        if self.positions.remove(key.as_ref()).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Removes the file once all requests are completed
+    pub fn remove(&self) -> io::Result<()> {
+        fs::remove_file(&self.path)
+    }
+
+}
+
+/// Page handler whose clones share the position
+///
+/// Used when items are handed out ahead of being processed, so that the
+/// position as of handing out an item can be saved (to a [`PositionFile`])
+/// once the item is processed.
+///
+/// [`PositionFile`]: struct.PositionFile.html
+#[derive(Clone, Default)]
+pub struct SharedPageHandler {
+    position: Arc<Mutex<Position>>,
+}
+
+impl SharedPageHandler {
+    pub fn new(position: Position) -> Self {
+        SharedPageHandler { position: Arc::new(Mutex::new(position)) }
+    }
+
+    pub fn position(&self) -> Position {
+        self.position.lock().unwrap().clone()
+    }
+}
+
+impl PageHandler for SharedPageHandler {
+    fn page_position(&self) -> Position {
+        self.position()
+    }
+
+    fn set_page_position(&mut self, pos: &Position) {
+        *self.position.lock().unwrap() = pos.clone();
+    }
+}
+
+use error::{Error, GraphQLError};
+use backoff::{Backoff, RateLimit};
 
 use std::fmt::Debug;
+use std::str;
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct DataWrapper<T> where T : Debug {
     data: Option<T>,
+    errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RateLimitData {
+    rate_limit: Option<RateLimit>,
 }
 
 use std::collections::{VecDeque, HashMap};
@@ -106,12 +238,20 @@ pub struct PageableRequest<S, Q, P> where S: AsRef<str>, Q : Pageable + Query, P
     client: reqwest::Client,
     page_handler: P,
     total_count: Option<usize>,
+    backoff: Backoff,
 }
 
 impl<S, Q, P> PageableRequest<S, Q, P> where S: AsRef<str>, Q : Pageable + Query, P : PageHandler {
-    pub fn new(url: S, token: S, query: Q, page_handler: P) -> Self {
-        let client = reqwest::Client::builder().build().unwrap();
-        PageableRequest{  url, query, token, queued: VecDeque::new(), next_page: Maybe::Maybe, client, page_handler, total_count: None }
+    /// Creates a request that starts from the page handler's current position
+    ///
+    /// Items up to the position are skipped, so it should only be saved once
+    /// the item handed out last is processed completely.
+    pub fn new(client: reqwest::Client, url: S, token: S, query: Q, page_handler: P, backoff: Backoff) -> Self {
+        let query = match page_handler.page_position().end_cursor {
+            Some(ref cursor) => query.after(cursor.clone()),
+            None => query,
+        };
+        PageableRequest{  url, query, token, queued: VecDeque::new(), next_page: Maybe::Maybe, client, page_handler, total_count: None, backoff }
     }
 }
 
@@ -122,7 +262,54 @@ impl<S, Q, P, T, Iter> PageableRequest<S, Q, P> where S: AsRef<str>,
                                                       T : HasPageInfo + HasTotalCount + IntoIterator<Item=Q::Item, IntoIter=Iter> + Debug {
 
     pub fn total_count(&self) -> Option<usize> {
-        self.total_count.clone()
+        self.total_count
+    }
+
+    fn fetch(&self, query: &str) -> Result<T, Error> where for<'de> T : Deserialize<'de> {
+        let mut map = HashMap::new();
+        map.insert("query", query);
+        let mut attempt = 0;
+        loop {
+            self.backoff.throttle();
+            let mut resp = self.client.post(self.url.as_ref())
+                .header(reqwest::header::Authorization(reqwest::header::Bearer { token: self.token.as_ref().into() }))
+                .json(&map)
+                .send()?;
+            let body = resp.text()?;
+            let status = resp.status();
+            let retry_after = resp.headers().get_raw("Retry-After").and_then(|v| v.one())
+                .and_then(|v| str::from_utf8(v).ok()).and_then(|v| v.trim().parse().ok());
+            // Secondary rate limits are reported with 403 and a message
+            let retry = match status {
+                StatusCode::BadGateway | StatusCode::ServiceUnavailable | StatusCode::GatewayTimeout |
+                StatusCode::TooManyRequests => true,
+                StatusCode::Forbidden => body.contains("rate limit"),
+                _ => false,
+            };
+            if retry && self.backoff.retry(attempt, &format!("Server responded with {}", status), retry_after) {
+                attempt += 1;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(Error::Status { status, body });
+            }
+            if let Ok(DataWrapper { data: Some(RateLimitData { rate_limit: Some(rate_limit) }), .. }) = serde_json::from_str(&body) {
+                self.backoff.update(rate_limit);
+            }
+            let response: DataWrapper<T> = serde_json::from_str(&body)?;
+            match response.data {
+                Some(data) => return Ok(data),
+                None => {
+                    let errors = response.errors.unwrap_or_default();
+                    if errors.iter().any(|e| e.kind.as_ref().map(|k| k == "RATE_LIMITED").unwrap_or(false)) &&
+                       self.backoff.wait_for_reset(attempt) {
+                        attempt += 1;
+                        continue;
+                    }
+                    return Err(Error::GraphQL(errors));
+                },
+            }
+        }
     }
 
 }
@@ -133,7 +320,7 @@ impl<S, Q, P, T, Iter> Iterator for PageableRequest<S, Q, P> where S: AsRef<str>
                                                                    Iter : Iterator<Item=Q::Item>,
                                                                    T : HasPageInfo + HasTotalCount + IntoIterator<Item=Q::Item, IntoIter=Iter> + Debug,
                                                                    for<'de> T : Deserialize<'de> {
-    type Item = Q::Item;
+    type Item = Result<Q::Item, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
         let mut pos = self.page_handler.page_position();
@@ -141,7 +328,7 @@ impl<S, Q, P, T, Iter> Iterator for PageableRequest<S, Q, P> where S: AsRef<str>
             Some(q) => {
                 pos.index += 1;
                 self.page_handler.set_page_position(&pos);
-                Some(q)
+                Some(Ok(q))
             },
             None => {
                 match self.next_page {
@@ -155,43 +342,153 @@ impl<S, Q, P, T, Iter> Iterator for PageableRequest<S, Q, P> where S: AsRef<str>
                     }
                 }
                 self.page_handler.set_page_position(&pos);
-                let mut map = HashMap::new();
-                map.insert("query", self.query.query());
-                match self.client.execute(self.client
-                    .post(self.url.as_ref())
-                    .header(reqwest::header::Authorization(reqwest::header::Bearer::from_str(self.token.as_ref()).unwrap()))
-                    .json(&map)
-                    .build()
-                    .unwrap()) {
-                    Err(e) => panic!("{}", e),
-                    Ok(mut resp) => {
-                        let response : DataWrapper<T> = resp.json().unwrap();
-                        let result = response.data.unwrap();
-                        if result.page_info().has_next_page {
-                            self.next_page = result.page_info().end_cursor.clone().into();
-                        } else {
-                            self.next_page = Maybe::None;
-                        }
-                        self.total_count = Some(result.total_count());
-                        let mut iter = result.into_iter().skip(pos.index as usize);
-
-                        match iter.next() {
-                            None => None,
-                            Some(item) => {
-                                loop {
-                                    match iter.next() {
-                                        Some(v) => self.queued.push_back(v),
-                                        None => break,
-                                    }
-                                }
-                                pos.index += 1;
-                                self.page_handler.set_page_position(&pos);
-                                Some(item)
-                            }
-                        }
+                let query = self.query.query();
+                let result = match self.fetch(&query) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        // Stop here, the position is kept so that the page can be retried
+                        self.next_page = Maybe::None;
+                        return Some(Err(Error::request(query, err)));
+                    },
+                };
+                if result.page_info().has_next_page {
+                    self.next_page = result.page_info().end_cursor.clone().into();
+                } else {
+                    self.next_page = Maybe::None;
+                }
+                self.total_count = Some(result.total_count());
+                let mut iter = result.into_iter().skip(pos.index as usize);
+
+                match iter.next() {
+                    // Everything on a page resumed from was processed already
+                    None if pos.index > 0 => match self.next_page {
+                        Maybe::Some(_) => self.next(),
+                        _ => None,
+                    },
+                    None => None,
+                    Some(item) => {
+                        self.queued.extend(iter);
+                        pos.index += 1;
+                        self.page_handler.set_page_position(&pos);
+                        Some(Ok(item))
                     }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use issues;
+    use testing::{Server, Route};
+    use backoff::tests::ManualClock;
+
+    fn issues_server() -> Server {
+        Server::start(vec![Route::new(vec!["issues(", "after: \"Y3Vyc29yOjI=\""], include_str!("../fixtures/github/issues_2.json")),
+                           Route::new(vec!["issues("], include_str!("../fixtures/github/issues_1.json"))])
+    }
+
+    fn request<'a>(server: &'a Server, page_handler: MemoryPageHandler) -> PageableRequest<&'a str, issues::Query<&'static str>, MemoryPageHandler> {
+        PageableRequest::new(reqwest::Client::new(), server.url.as_str(), "token", issues::Query::new("octo-org", "octoroku"), page_handler, Backoff::default())
+    }
+
+    #[test]
+    fn pagination() {
+        let server = issues_server();
+        let mut req = request(&server, MemoryPageHandler::default());
+        let numbers: Vec<_> = req.by_ref().map(|issue| issue.unwrap().number).collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert_eq!(req.total_count(), Some(3));
+        assert_eq!(server.queries().len(), 2);
+        let pos = req.page_handler.page_position();
+        assert_eq!(pos.end_cursor, Some("Y3Vyc29yOjI=".into()));
+        assert_eq!(pos.index, 1);
+    }
+
+    #[test]
+    fn resumption_within_page() {
+        let server = issues_server();
+        let mut page_handler = MemoryPageHandler::default();
+        // Issue #2 was handed out and processed last
+        page_handler.set_page_position(&Position { index: 2, end_cursor: None });
+        let numbers: Vec<_> = request(&server, page_handler).map(|issue| issue.unwrap().number).collect();
+        assert_eq!(numbers, vec![3]);
+    }
+
+    #[test]
+    fn resumption_from_cursor() {
+        let server = issues_server();
+        let mut page_handler = MemoryPageHandler::default();
+        page_handler.set_page_position(&Position { index: 0, end_cursor: Some("Y3Vyc29yOjI=".into()) });
+        let numbers: Vec<_> = request(&server, page_handler).map(|issue| issue.unwrap().number).collect();
+        assert_eq!(numbers, vec![3]);
+        assert_eq!(server.queries().len(), 1);
+    }
+
+    #[test]
+    fn graphql_errors() {
+        let server = Server::start(vec![Route::new(vec!["issues("], include_str!("../fixtures/github/errors.json"))]);
+        let mut req = request(&server, MemoryPageHandler::default());
+        match req.next() {
+            Some(Err(Error::Request { request, error })) => {
+                assert!(request.contains("octoroku"));
+                match *error {
+                    Error::GraphQL(ref errors) => {
+                        assert_eq!(errors.len(), 1);
+                        assert_eq!(errors[0].kind, Some("NOT_FOUND".into()));
+                    },
+                    ref err => panic!("unexpected error {:?}", err),
+                }
+            },
+            other => panic!("unexpected result {:?}", other.map(|r| r.map(|issue| issue.number))),
+        }
+        assert!(req.next().is_none());
+    }
+
+    #[test]
+    fn failed_page_keeps_position() {
+        let server = Server::start(vec![Route::failing(vec!["issues(", "after: \"Y3Vyc29yOjI=\""]),
+                                        Route::new(vec!["issues("], include_str!("../fixtures/github/issues_1.json"))]);
+        let mut req = request(&server, MemoryPageHandler::default());
+        assert_eq!(req.next().unwrap().unwrap().number, 1);
+        assert_eq!(req.next().unwrap().unwrap().number, 2);
+        match req.next() {
+            Some(Err(Error::Request { error, .. })) => match *error {
+                Error::Status { status, .. } => assert_eq!(status, StatusCode::InternalServerError),
+                ref err => panic!("unexpected error {:?}", err),
+            },
+            other => panic!("unexpected result {:?}", other.map(|r| r.map(|issue| issue.number))),
+        }
+        assert!(req.next().is_none());
+        let pos = req.page_handler.page_position();
+        assert_eq!(pos.end_cursor, Some("Y3Vyc29yOjI=".into()));
+        assert_eq!(pos.index, 0);
+    }
+
+    #[test]
+    fn secondary_rate_limit() {
+        let server = Server::start(vec![Route {
+            status: 403,
+            ..Route::new(vec!["issues("], r#"{"message": "You have exceeded a secondary rate limit."}"#).header("Retry-After", "5")
+        }]);
+        let clock = ManualClock::new();
+        let backoff = Backoff::with_clock(clock.clone());
+        backoff.listen(|_| ());
+        let mut req = PageableRequest::new(reqwest::Client::new(), server.url.as_str(), "token", issues::Query::new("octo-org", "octoroku"),
+                                           MemoryPageHandler::default(), backoff);
+        match req.next() {
+            Some(Err(Error::Request { error, .. })) => match *error {
+                Error::Status { status, .. } => assert_eq!(status, StatusCode::Forbidden),
+                ref err => panic!("unexpected error {:?}", err),
+            },
+            other => panic!("unexpected result {:?}", other.map(|r| r.map(|issue| issue.number))),
+        }
+        // Every retry waits as long as asked to
+        assert_eq!(server.queries().len(), 7);
+        assert_eq!(clock.slept(), 6 * 5);
+    }
+
+}
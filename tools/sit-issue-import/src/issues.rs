@@ -1,7 +1,13 @@
 use std::fmt::Display;
 use askama::Template;
-use graphql::{Query as GraphQLQuery, Pageable, HasPageInfo, HasTotalCount, PageInfo, Paged};
+use graphql::{Query as GraphQLQuery, Pageable, HasPageInfo, HasTotalCount, PageInfo, Paged, Nodes, Connection};
+use provider::Label;
+use milestones::MilestoneRef;
 use author::Author;
+use edits::UserContentEdit;
+use reactions::Reaction;
+use filter::Filter;
+use serde_json;
 
 #[derive(Template)]
 #[template(path = "issues.graphql")]
@@ -9,11 +15,35 @@ pub struct Query<S> where S : AsRef<str> + Display {
     pub owner: S,
     pub repository: S,
     pub after: Option<String>,
+    /// GraphQL string of the earliest update time
+    pub since: Option<String>,
+    /// GraphQL list of states to include
+    pub states: Option<String>,
+    /// GraphQL list of labels, any of which has to be set
+    pub labels: Option<String>,
+    /// GraphQL string of the author's login
+    pub created_by: Option<String>,
 }
 
 impl<S> Query<S> where S : AsRef<str> + Display {
     pub fn new(owner: S, repository: S) -> Self {
-        Query { owner, repository, after: None }
+        Query { owner, repository, after: None, since: None, states: None, labels: None, created_by: None }
+    }
+
+    pub fn since(self, since: Option<String>) -> Self {
+        Query { since: since.and_then(|since| serde_json::to_string(&since).ok()), ..self }
+    }
+
+    /// Narrows down the query to what GitHub can filter by itself
+    pub fn filter(self, filter: &Filter) -> Self {
+        let states = match filter.closed {
+            None => None,
+            Some(false) => Some("[OPEN]"),
+            Some(true) => Some("[CLOSED]"),
+        };
+        let labels = if filter.labels.is_empty() { None } else { serde_json::to_string(&filter.labels).ok() };
+        let created_by = filter.author.as_ref().and_then(|author| serde_json::to_string(author).ok());
+        Query { states: states.map(String::from), labels, created_by, ..self }
     }
 
 }
@@ -28,13 +58,46 @@ impl<S> GraphQLQuery for Query<S> where S : AsRef<str> + Display + Copy {
 impl<S> Pageable for Query<S> where S : AsRef<str> + Display + Copy {
     type Item = Issue;
     fn after(&self, cursor: String) -> Self {
-        Query{ owner: self.owner, repository: self.repository, after: Some(cursor) }
+        Query{ owner: self.owner, repository: self.repository, after: Some(cursor), since: self.since.clone(),
+              states: self.states.clone(), labels: self.labels.clone(), created_by: self.created_by.clone() }
+    }
+}
+
+/// Query for a single issue by its number
+#[derive(Template)]
+#[template(path = "issue.graphql")]
+pub struct ItemQuery<S> where S : AsRef<str> + Display {
+    pub owner: S,
+    pub repository: S,
+    pub number: usize,
+}
+
+impl<S> ItemQuery<S> where S : AsRef<str> + Display {
+    pub fn new(owner: S, repository: S, number: usize) -> Self {
+        ItemQuery { owner, repository, number }
+    }
+}
+
+impl<S> GraphQLQuery for ItemQuery<S> where S : AsRef<str> + Display + Copy {
+    type Result = ItemResponse;
+    fn query(&self) -> String {
+        self.render().unwrap()
+    }
+}
+
+/// There's only one page
+impl<S> Pageable for ItemQuery<S> where S : AsRef<str> + Display + Copy {
+    type Item = Issue;
+    fn after(&self, _cursor: String) -> Self {
+        ItemQuery { owner: self.owner, repository: self.repository, number: self.number }
     }
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Issue {
+    /// Node ID
+    pub id: String,
     pub number: usize,
     pub url: String,
     pub state: String,
@@ -44,6 +107,11 @@ pub struct Issue {
     pub updated_at: String,
     pub closed_at: Option<String>,
     pub author: Option<Author>,
+    pub user_content_edits: Option<Connection<UserContentEdit>>,
+    pub reactions: Option<Connection<Reaction>>,
+    pub milestone: Option<MilestoneRef>,
+    pub assignees: Nodes<Author>,
+    pub labels: Nodes<Label>,
 }
 
 
@@ -79,3 +147,89 @@ impl IntoIterator for Response {
         self.repository.issues.into_iter()
     }
 }
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueItemNode {
+    issue: Issue,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemResponse {
+    repository: IssueItemNode,
+    #[serde(skip)]
+    page_info: PageInfo,
+}
+
+impl HasPageInfo for ItemResponse {
+    fn page_info(&self) -> &PageInfo {
+        &self.page_info
+    }
+}
+
+impl HasTotalCount for ItemResponse {
+    fn total_count(&self) -> usize {
+        1
+    }
+}
+
+impl IntoIterator for ItemResponse {
+    type Item = Issue;
+    type IntoIter = ::std::option::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Some(self.repository.issue).into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn deserialize() {
+        let page: serde_json::Value = serde_json::from_str(include_str!("../fixtures/github/issues_1.json")).unwrap();
+        let response: Response = serde_json::from_value(page["data"].clone()).unwrap();
+        assert_eq!(response.total_count(), 3);
+        assert_eq!(response.page_info().end_cursor, Some("Y3Vyc29yOjI=".into()));
+        let issues: Vec<_> = response.into_iter().collect();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].number, 1);
+        assert_eq!(issues[0].author.as_ref().map(|a| a.login.as_str()), Some("hubot"));
+        assert_eq!(issues[0].assignees.nodes[0].login, "octocat");
+        assert_eq!(issues[0].labels.nodes[0].color, Some("ee0701".into()));
+        assert!(issues[0].milestone.is_none());
+    }
+
+    #[test]
+    fn query() {
+        let query = Query::new("octo-org", "octoroku").since(Some("2018-04-01T00:00:00Z".into())).after("Y3Vyc29yOjI=".into());
+        let text = GraphQLQuery::query(&query);
+        assert!(text.contains(r#"repository(owner: "octo-org", name: "octoroku")"#));
+        assert!(text.contains(r#"filterBy: {since: "2018-04-01T00:00:00Z"}"#));
+        assert!(text.contains(r#"after: "Y3Vyc29yOjI=""#));
+    }
+
+    #[test]
+    fn filtered_query() {
+        let filter = Filter { closed: Some(false), labels: vec!["bug".into(), "good first issue".into()],
+                              author: Some("octocat".into()), ..Filter::default() };
+        let query = Query::new("octo-org", "octoroku").since(Some("2018-04-01T00:00:00Z".into())).filter(&filter);
+        let text = GraphQLQuery::query(&query.after("Y3Vyc29yOjI=".into()));
+        assert!(text.contains(r#"states: [OPEN]"#));
+        assert!(text.contains(r#"labels: ["bug","good first issue"]"#));
+        assert!(text.contains(r#"filterBy: {since: "2018-04-01T00:00:00Z", createdBy: "octocat"}"#));
+    }
+
+    #[test]
+    fn escaped_query() {
+        let filter = Filter { author: Some(r#"octocat"){"#.into()), ..Filter::default() };
+        let query = Query::new("octo-org", "octoroku").since(Some(r#"2018"\"#.into())).filter(&filter);
+        let text = GraphQLQuery::query(&query);
+        assert!(text.contains(r#"filterBy: {since: "2018\"\\", createdBy: "octocat\"){"}"#));
+    }
+
+}
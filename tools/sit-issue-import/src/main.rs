@@ -1,55 +1,79 @@
+// Matching on `&Variant` is how the crate destructures borrowed enums
+#![allow(clippy::match_ref_pats)]
+
 extern crate serde;
 #[macro_use] extern crate serde_derive;
 extern crate serde_json;
+extern crate serde_yaml;
 
 extern crate reqwest;
 
 #[macro_use] extern crate clap;
 extern crate console;
 
-#[macro_use] extern crate askama;
+extern crate askama;
 
 extern crate xdg;
 extern crate config;
 
 extern crate pbr;
+extern crate crossbeam;
 
 extern crate regex;
 
+extern crate time;
+
+extern crate tar;
+extern crate flate2;
+
 extern crate git2;
 
 extern crate sit_core;
 
+#[cfg(test)] extern crate tempdir;
+
+mod error;
+use error::Error;
+mod backoff;
 mod graphql;
 
 mod issues;
 mod pull_requests;
 mod timeline;
+mod milestones;
+mod connection;
 mod author;
 mod actor;
+mod edits;
+mod reactions;
 mod files;
-use files::Files;
+mod sync;
+mod pool;
+mod filter;
+mod dry_run;
+
+mod provider;
+use provider::Kind;
+mod writer;
+mod github;
+mod gitlab;
+mod archive;
+mod token;
+mod module;
+#[cfg(test)]
+mod testing;
 
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use clap::{App, Arg};
 
-const GITHUB_GRAPHQL : &str = "https://api.github.com/graphql";
-
 fn main() {
     ::std::process::exit(real_main())
 }
 
-#[derive(Deserialize)]
-struct GitHubProviderConfig {
-    token: String,
-}
-
-#[derive(Deserialize)]
-struct Config {
-    github: Option<GitHubProviderConfig>,
-}
-
 fn real_main() -> i32 {
 
     let cwd = env::current_dir().expect("can't get currenGt working directory");
@@ -70,11 +94,101 @@ fn real_main() -> i32 {
                  .short("c")
                  .long("config")
                  .takes_value(true)
-                 .help("Configuration file"))
+                 .help("Configuration file (~/.config/sit-issue-import/config.json by default)"))
+        .arg(Arg::with_name("sync")
+                 .long("sync")
+                 .help("Only fetch issues and pull requests updated since the last import from the same source"))
+        .arg(Arg::with_name("resume")
+                 .long("resume")
+                 .help("Resume an interrupted import from where it stopped"))
+        .arg(Arg::with_name("jobs")
+                 .short("j")
+                 .long("jobs")
+                 .takes_value(true)
+                 .value_name("N")
+                 .default_value("4")
+                 .validator(|jobs| match jobs.parse::<usize>() {
+                     Ok(jobs) if jobs > 0 => Ok(()),
+                     _ => Err(format!("{} is not a positive number", jobs)),
+                 })
+                 .help("Fetch up to this many timelines at once"))
+        .arg(Arg::with_name("closer_comments")
+                 .long("closer-comments")
+                 .help("Also show the commit or pull request that closed an issue as a comment on it"))
+        .arg(Arg::with_name("dry_run")
+                 .long("dry-run")
+                 .help("Fetch everything but only print what would be imported instead of creating records"))
+        .arg(Arg::with_name("report")
+                 .long("report")
+                 .takes_value(true)
+                 .requires("dry_run")
+                 .help("With --dry-run, also write the summary to this file as JSON"))
+        .arg(Arg::with_name("issues_only")
+                 .long("issues-only")
+                 .conflicts_with("prs_only")
+                 .help("Only import issues"))
+        .arg(Arg::with_name("prs_only")
+                 .long("prs-only")
+                 .help("Only import pull (merge) requests"))
+        .arg(Arg::with_name("state")
+                 .long("state")
+                 .takes_value(true)
+                 .possible_values(&["open", "closed"])
+                 .help("Only import open or closed (including merged) issues and pull requests"))
+        .arg(Arg::with_name("label")
+                 .long("label")
+                 .takes_value(true)
+                 .multiple(true)
+                 .number_of_values(1)
+                 .help("Only import issues and pull requests with this label (can be repeated to allow any of several)"))
+        .arg(Arg::with_name("author")
+                 .long("author")
+                 .takes_value(true)
+                 .value_name("LOGIN")
+                 .help("Only import issues and pull requests opened by this user"))
+        .args(&[("created_after", "created-after", "Only import issues and pull requests created at or after this date"),
+                ("created_before", "created-before", "Only import issues and pull requests created before this date"),
+                ("updated_after", "updated-after", "Only import issues and pull requests updated at or after this date"),
+                ("updated_before", "updated-before", "Only import issues and pull requests updated before this date")]
+              .iter().map(|&(name, long, help)| Arg::with_name(name)
+                          .long(long)
+                          .takes_value(true)
+                          .value_name("DATE")
+                          .validator(|date| filter::timestamp(&date).map(|_| ()))
+                          .help(help))
+              .collect::<Vec<_>>())
+        .arg(Arg::with_name("numbers")
+                 .long("numbers")
+                 .takes_value(true)
+                 .value_name("FROM..TO")
+                 .validator(|range| filter::numbers(&range).map(|_| ()))
+                 .help("Only import issues and pull requests numbered within this range (either end can be omitted)"))
+        .arg(Arg::with_name("module_source")
+                 .long("module-source")
+                 .takes_value(true)
+                 .value_name("PATH|URL")
+                 .conflicts_with("no_module")
+                 .help("Where to install the issue-tracking module from: a git repository or a local copy (sit-fyi/issue-tracking on GitHub by default)"))
+        .arg(Arg::with_name("module_revision")
+                 .long("module-revision")
+                 .takes_value(true)
+                 .value_name("REV")
+                 .conflicts_with("no_module")
+                 .help("Revision of the issue-tracking module to check out when installing it"))
+        .arg(Arg::with_name("force_module_checkout")
+                 .long("force-module-checkout")
+                 .requires("module_revision")
+                 .help("Discard local changes to the issue-tracking module when checking out --module-revision in it"))
+        .arg(Arg::with_name("no_module")
+                 .long("no-module")
+                 .help("Don't install the issue-tracking module"))
         .arg(Arg::with_name("SOURCE")
                  .required(true)
                  .long_help("Where to import from\
-                 \nFor GitHub: https://github.com/OWNER/REPO"))
+                 \nFor GitHub: https://github.com/OWNER/REPO (or https://HOST/OWNER/REPO of a configured GitHub Enterprise host)\
+                 \nFor a single GitHub issue or pull request: https://github.com/OWNER/REPO/issues/N or https://github.com/OWNER/REPO/pull/N\
+                 \nFor GitLab: https://gitlab.com/GROUP/PROJECT (or URL of a self-hosted instance)\
+                 \nFor a GitHub migration archive: path to the .tar.gz file or the directory it was extracted into"))
         .get_matches();
 
     let working_dir = matches.value_of("working_directory").map(PathBuf::from).unwrap_or(cwd);
@@ -87,222 +201,653 @@ fn real_main() -> i32 {
 
     let source = matches.value_of("SOURCE").unwrap();
 
-    if source.starts_with("https://github.com/") {
-
-        git2::Repository::clone("https://github.com/sit-it/issue-tracking.git", repo.modules_path().join("issue-tracking")).unwrap();
-
-        if !matches.is_present("config") {
-            eprintln!("-c/--config required for GitHub to configure the token");
+    let configuration: Option<provider::Config> = matches.value_of("config").map(PathBuf::from)
+        .or_else(token::default_config)
+        .map(|file| {
+            let mut cfg = config::Config::new();
+            cfg.merge(config::File::from(file.as_path()).required(true)).unwrap();
+            let mut configuration: provider::Config = cfg.try_into().expect("Can't deserialize configuration");
+            configuration.file = Some(file);
+            configuration
+        });
+
+    let provider = match provider::open(source, configuration.as_ref()) {
+        None => {
+            eprintln!("Unrecognized source provider: {}", source);
+            return -1;
+        },
+        Some(Err(err)) => {
+            eprintln!("{}", err);
             return 1;
+        },
+        Some(Ok(provider)) => provider,
+    };
+
+    let mut options = Options {
+        sync: matches.is_present("sync"),
+        resume: matches.is_present("resume"),
+        jobs: matches.value_of("jobs").unwrap().parse().unwrap(),
+        closer_comments: matches.is_present("closer_comments"),
+        report: if matches.is_present("dry_run") { Some(Arc::new(Mutex::new(dry_run::Report::default()))) } else { None },
+        filter: filter::Filter {
+            kind: if matches.is_present("issues_only") {
+                Some(Kind::Issue)
+            } else if matches.is_present("prs_only") {
+                Some(Kind::MergeRequest)
+            } else {
+                None
+            },
+            closed: matches.value_of("state").map(|state| state == "closed"),
+            labels: matches.values_of("label").map(|labels| labels.map(String::from).collect()).unwrap_or_default(),
+            author: matches.value_of("author").map(String::from),
+            created_after: matches.value_of("created_after").map(|date| filter::timestamp(date).unwrap()),
+            created_before: matches.value_of("created_before").map(|date| filter::timestamp(date).unwrap()),
+            updated_after: matches.value_of("updated_after").map(|date| filter::timestamp(date).unwrap()),
+            updated_before: matches.value_of("updated_before").map(|date| filter::timestamp(date).unwrap()),
+            numbers: matches.value_of("numbers").map(|range| filter::numbers(range).unwrap()).unwrap_or_default(),
+        },
+    };
+
+    // A single issue or pull request is imported as if everything else was filtered out
+    if let Some((kind, number)) = provider.selection() {
+        options.filter.kind = Some(kind);
+        options.filter.numbers = (Some(number), Some(number));
+    }
+
+    if options.report.is_none() && !matches.is_present("no_module") {
+        let module_path = repo.modules_path().join("issue-tracking");
+        let module_source = matches.value_of("module_source").unwrap_or(module::DEFAULT_SOURCE);
+        match module::install(module_source, matches.value_of("module_revision"), &module_path,
+                              matches.is_present("force_module_checkout")) {
+            Ok(module::Installation::Existing) => (),
+            Ok(module::Installation::CheckedOut) =>
+                eprintln!("Checked out {} in the issue-tracking module", matches.value_of("module_revision").unwrap()),
+            Ok(_) => eprintln!("Installed the issue-tracking module from {}", module_source),
+            Err(err) => {
+                eprintln!("Error: {}\nUse --module-source to install it from elsewhere or --no-module to skip it", err);
+                return 1;
+            },
         }
-        let mut cfg = config::Config::new();
-        cfg.merge(config::File::with_name(matches.value_of("config").unwrap()).required(true)).unwrap();
-        let configuration: Config = cfg.try_into().expect("Can't deserialize configuration");
+    }
 
-        if configuration.github.is_none() {
-            eprintln!("github provider configuration required");
-            return -1;
+    let state_path = sync::State::path(&repo, provider.name(), provider.path());
+    let positions_path = state_path.with_extension("pages.json");
+
+    if let Err(err) = import(&repo, &*provider, &state_path, &positions_path, &options) {
+        eprintln!("\nError: {}", err);
+        if options.report.is_none() && positions_path.exists() {
+            eprintln!("Run again with --resume to continue from where the import stopped");
         }
+        return 1;
+    }
 
-        let re = regex::Regex::new("https://github.com/([^/]+)/([^/]+)(\\.git)?").unwrap();
-        let captures = re.captures(source).unwrap();
-        if captures.len() < 3 {
-            println!("Invalid source URL");
-            return 1;
+    if let Some(ref report) = options.report {
+        let report = report.lock().unwrap();
+        report.markdown(io::stdout()).expect("can't print the dry run summary");
+        if let Some(file) = matches.value_of("report") {
+            let file = fs::File::create(file).expect("can't create the report file");
+            serde_json::to_writer_pretty(file, &*report).expect("can't write the report file");
         }
-        let owner = &captures[1];
-        let repository = &captures[2];
+    }
+
+    0
+
+}
+
+/// How the import is performed
+struct Options {
+    /// Only import what was updated since the last import
+    sync: bool,
+    /// Continue from the saved page positions
+    resume: bool,
+    /// How many timelines to fetch at once
+    jobs: usize,
+    /// Also record what closed an issue as a comment
+    closer_comments: bool,
+    /// Present during a dry run, collects what would be imported
+    report: Option<Arc<Mutex<dry_run::Report>>>,
+    /// Which issues and merge requests to import
+    filter: filter::Filter,
+}
+
+fn import(repo: &sit_core::Repository, provider: &provider::Provider, state_path: &Path, positions_path: &Path,
+          options: &Options) -> Result<(), Error> {
 
-        let mut req0 = graphql::PageableRequest::new(GITHUB_GRAPHQL, &configuration.github.as_ref().unwrap().token, issues::Query::new(owner, repository), graphql::MemoryPageHandler::default());
-        let req = graphql::PageableRequest::new(GITHUB_GRAPHQL, &configuration.github.as_ref().unwrap().token, issues::Query::new(owner, repository), graphql::MemoryPageHandler::default());
+    let mut state = sync::State::load(state_path)?;
+    let (issues_since, mrs_since) = if options.sync {
+        (state.issues.clone(), state.pull_requests.clone())
+    } else {
+        (None, None)
+    };
+
+    let filter = &options.filter;
+    let count = |kind, since: &Option<String>| if filter.includes(kind) { provider.count(kind, since.as_ref(), filter) } else { Ok(0) };
+    let issues = count(Kind::Issue, &issues_since)? as u64;
+    let mrs = count(Kind::MergeRequest, &mrs_since)? as u64;
+    // There are few milestones, so they are always fetched in full
+    let milestones = provider.milestones().collect::<Result<Vec<_>, _>>()?;
+    // A single issue or merge request only brings its own milestone along, which is imported with it
+    let single = filter.number().is_some();
+    let listed_milestones = if single { 0 } else { milestones.len() as u64 };
+
+    if issues + mrs + listed_milestones == 0 {
+        // Nothing to see
+        return Ok(())
+    }
+
+    // A dry run doesn't save page positions
+    let mut positions = match options.report {
+        Some(_) => None,
+        None if options.resume => Some(graphql::PositionFile::open(positions_path)?),
+        None => Some(graphql::PositionFile::create(positions_path)?),
+    };
+    // Where the timelines of items that were being imported when the import got interrupted stopped
+    let interrupted = positions.clone();
+
+    let progress_bar = Arc::new(Mutex::new(pbr::ProgressBar::new(issues + mrs + listed_milestones)));
+    progress_bar.lock().unwrap().set(0);
+
+    if let Some(backoff) = provider.backoff() {
+        let progress_bar = progress_bar.clone();
+        backoff.listen(move |message| {
+            let mut progress_bar = progress_bar.lock().unwrap();
+            progress_bar.message(message);
+            progress_bar.tick();
+        });
+    }
+
+    progress_bar.lock().unwrap().message("[ Importing milestones ] ");
 
-        let mut preq0 = graphql::PageableRequest::new(GITHUB_GRAPHQL, &configuration.github.as_ref().unwrap().token, pull_requests::Query::new(owner, repository), graphql::MemoryPageHandler::default());
-        let preq = graphql::PageableRequest::new(GITHUB_GRAPHQL, &configuration.github.as_ref().unwrap().token, pull_requests::Query::new(owner, repository), graphql::MemoryPageHandler::default());
+    for milestone in milestones.iter().filter(|_| !single) {
+        progress_bar.lock().unwrap().message(&format!("[ {} ] ", milestone.title));
+        import_milestone(repo, milestone, options)?;
+        progress_bar.lock().unwrap().inc();
+    }
 
-        use sit_core::Item;
+    for &(kind, key, since) in [(Kind::Issue, "issues", &issues_since), (Kind::MergeRequest, "merge_requests", &mrs_since)].iter() {
 
-        if req0.next().is_none() {
-            // Nothing to see
-            return 0
+        if !filter.includes(kind) {
+            continue;
         }
-        let prs = match preq0.next() {
-            None => 0 as u64,
-            Some(_) => preq0.total_count().unwrap() as u64,
-        };
 
-        let mut progress_bar = pbr::ProgressBar::new(req0.total_count().unwrap() as u64 + prs);
-
-        progress_bar.message("[ Importing issues ] ");
-        progress_bar.set(0);
-
-        for ext_issue in req {
-            let issue = repo.new_named_item(format!("github-issue-{}", ext_issue.number)).unwrap();
-
-            progress_bar.message(&format!("[ #{} {} ] ", ext_issue.number, ext_issue.title));
-
-            issue.new_record(Files::from(vec![(".type/SummaryChanged", &b""[..]),
-                                              (".timestamp", ext_issue.created_at.as_bytes()),
-                                              ("text", ext_issue.title.as_bytes()),
-                                              (".imported", ext_issue.url.as_bytes())])
-                                 .followed_by(ext_issue.author.as_ref().into())
-                                 .into_iter(), true).unwrap();
-
-            issue.new_record(Files::from(vec![(".type/DetailsChanged", &b""[..]),
-                                              (".timestamp", ext_issue.created_at.as_bytes()),
-                                              ("text", ext_issue.body.as_bytes()),
-                                              (".imported", ext_issue.url.as_bytes())])
-                                 .followed_by(ext_issue.author.as_ref().into())
-                                 .into_iter(), true).unwrap();
-
-            let treq = graphql::PageableRequest::new(GITHUB_GRAPHQL, &configuration.github.as_ref().unwrap().token,
-                                                     timeline::Query::new(owner, repository, timeline::Kind::Issue, ext_issue.number), graphql::MemoryPageHandler::default());
-
-            for item in treq {
-
-                match item {
-                    timeline::TimelineItem::IssueComment { created_at, body, author, url, .. } => {
-                        issue.new_record(Files::from(vec![(".type/Commented", &b""[..]),
-                                              (".timestamp", created_at.as_bytes()),
-                                              ("text", body.as_bytes()),
-                                              (".imported", url.as_bytes())])
-                                             .followed_by(author.as_ref().into())
-                                             .into_iter(), true).unwrap();
-                    },
-                    timeline::TimelineItem::ClosedEvent { created_at, actor, closer } => {
-                         issue.new_record(Files::from(vec![(".type/Closed", &b""[..]),
-                                               (".timestamp", created_at.as_bytes()),
-                                               (".imported", ext_issue.url.as_bytes())])
-                                              .followed_by(closer.as_ref().into())
-                                              .followed_by(actor.as_ref().into())
-                                              .into_iter(), true).unwrap();
-                    },
-                    timeline::TimelineItem::ReopenedEvent { created_at, actor } => {
-                         issue.new_record(Files::from(vec![(".type/Reopened", &b""[..]),
-                                               (".timestamp", created_at.as_bytes()),
-                                               (".imported", ext_issue.url.as_bytes())])
-                                              .followed_by(actor.as_ref().into())
-                                              .into_iter(), true).unwrap();
-                    },
-                    timeline::TimelineItem::LockedEvent { created_at, actor } => {
-                         issue.new_record(Files::from(vec![(".type/Locked", &b""[..]),
-                                               (".timestamp", created_at.as_bytes()),
-                                               (".imported", ext_issue.url.as_bytes())])
-                                              .followed_by(actor.as_ref().into())
-                                              .into_iter(), true).unwrap();
-                    },
-                    timeline::TimelineItem::UnlockedEvent { created_at, actor } => {
-                         issue.new_record(Files::from(vec![(".type/Unlocked", &b""[..]),
-                                               (".timestamp", created_at.as_bytes()),
-                                               (".imported", ext_issue.url.as_bytes())])
-                                              .followed_by(actor.as_ref().into())
-                                              .into_iter(), true).unwrap();
-
-                    },
-                    _ => (),
+        progress_bar.lock().unwrap().message(match kind {
+            Kind::Issue => "[ Importing issues ] ",
+            Kind::MergeRequest => "[ Importing merge requests ] ",
+        });
+
+        let mut mark = None;
+        let mut failed = false;
+
+        // Items are listed ahead of being imported, so the position as of listing
+        // an item is only saved once it's imported. The same goes for timelines,
+        // which are fetched ahead of being imported.
+        let list = graphql::SharedPageHandler::new(positions.as_ref().map(|p| p.position(key)).unwrap_or_default());
+        let items = provider.items(kind, since.as_ref(), filter, Box::new(list.clone()))
+            .map(|ext_item| ext_item.map(|ext_item| {
+                let timeline = interrupted.as_ref().map(|p| p.position(timeline_key(&ext_item))).unwrap_or_default();
+                (ext_item, list.position(), timeline)
+            }))
+            .filter(|ext_item| match ext_item {
+                &Ok((ref ext_item, _, _)) => filter.matches(ext_item),
+                _ => true,
+            });
+
+        let batches = pool::batches(items, provider.timeline_batch());
+        let result = pool::ordered(options.jobs, batches, |batch| {
+            let ext_items: Vec<_> = batch.iter().map(|&(ref ext_item, _, ref timeline)| (ext_item, timeline.clone())).collect();
+            provider.timelines(&ext_items)
+        }, |batch, timelines| {
+            for ((ext_item, position, _), timeline) in batch.into_iter().zip(timelines) {
+                progress_bar.lock().unwrap().message(&format!("[ #{} {} ] ", ext_item.number, ext_item.title));
+                let timeline_key = timeline_key(&ext_item);
+                if single {
+                    for milestone in milestones.iter().filter(|milestone| ext_item.milestone.as_ref() == Some(&milestone.name)) {
+                        import_milestone(repo, milestone, options)?;
+                    }
                 }
+                let result = import_item(repo, provider, &ext_item, timeline, options, |timeline| match positions {
+                        Some(ref mut positions) => positions.set_position(timeline_key.as_str(), timeline.clone()).map_err(Error::from),
+                        None => Ok(()),
+                    })
+                    .map_err(|err| Error::item(format!("#{} ({})", ext_item.number, ext_item.url), err))
+                    .and_then(|_| match positions {
+                        Some(ref mut positions) => {
+                            positions.forget(&timeline_key)?;
+                            positions.set_position(key, position).map_err(Error::from)
+                        },
+                        None => Ok(()),
+                    });
+                failed = result.is_err();
+                sync::advance(&mut mark, &ext_item.updated_at);
+                progress_bar.lock().unwrap().inc();
+                result?;
+            }
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            // If it was listing that failed, everything listed is imported by now
+            // and listing can be resumed right where it stopped
+            if let (false, Some(ref mut positions)) = (failed, positions.as_mut()) {
+                positions.set_position(key, list.position())?;
             }
+            return Err(err);
+        }
 
-            progress_bar.inc();
+        if let Some(ref updated_at) = mark {
+            match kind {
+                Kind::Issue => sync::advance(&mut state.issues, updated_at),
+                Kind::MergeRequest => sync::advance(&mut state.pull_requests, updated_at),
+            }
         }
+    }
 
-       let client = reqwest::Client::builder().build().unwrap();
+    progress_bar.lock().unwrap().finish();
 
-        progress_bar.message("[ Importing pull requests ] ");
+    // Filtered out items weren't imported, so a later --sync has to consider them again
+    if options.report.is_none() && filter.is_empty() {
+        state.save(state_path)?;
+    }
 
-        for pr in preq {
+    if let Some(ref positions) = positions {
+        positions.remove()?;
+    }
 
-            let issue = repo.new_named_item(format!("github-pr-{}", pr.number)).unwrap();
-            progress_bar.message(&format!("[ #{} {} ] ", pr.number, pr.title));
+    Ok(())
+}
 
-            issue.new_record(Files::from(vec![(".type/SummaryChanged", &b""[..]),
-                                  (".timestamp", pr.created_at.as_bytes()),
-                                  ("text", pr.title.as_bytes()),
-                                  (".imported", pr.url.as_bytes())])
-                                 .followed_by(pr.author.as_ref().into())
-                                 .into_iter(), true).unwrap();
+fn import_milestone(repo: &sit_core::Repository, milestone: &provider::Milestone, options: &Options) -> Result<(), Error> {
+    sync::Target::open(repo, &milestone.name, options.report.as_ref())
+        .and_then(|mut target| writer::milestone(&mut target, milestone))
+        .map_err(|err| Error::item(milestone.url.as_str(), err.into()))
+}
 
-            let mut response = client.get(&format!("{}.patch", pr.url)).send().unwrap();
-            let patch = response.text().unwrap();
+/// Key of the position in the timeline of an item being imported
+fn timeline_key(ext_item: &provider::Item) -> String {
+    format!("timeline/{}", ext_item.name)
+}
 
-            let mut mr_rec = vec![(".type/DetailsChanged", &b""[..]),
-                                  (".type/MergeRequested", &b""[..]),
-                                  (".timestamp", pr.created_at.as_bytes()),
-                                  ("text", pr.body.as_bytes()),
-                                  (".imported", pr.url.as_bytes())];
+/// Imports an item along with as much of its timeline as was fetched
+///
+/// `imported` is given the position to resume the timeline from as events are imported.
+fn import_item<F>(repo: &sit_core::Repository, provider: &provider::Provider, ext_item: &provider::Item,
+                  timeline: provider::Timeline, options: &Options, mut imported: F) -> Result<(), Error>
+    where F: FnMut(&graphql::Position) -> Result<(), Error> {
+    let mut target = sync::Target::open(repo, &ext_item.name, options.report.as_ref())?;
+
+    writer::item(&mut target, provider, ext_item)?;
+
+    let mut events = timeline.events.iter().peekable();
+    while let Some(&(ref event, ref position)) = events.next() {
+        writer::event(&mut target, ext_item, event, options.closer_comments)?;
+        // Events made of the same entry can only be resumed from once all of them are imported
+        if events.peek().map(|&&(_, ref next)| next != position).unwrap_or(true) {
+            imported(position)?;
+        }
+    }
+    if let Some(err) = timeline.error {
+        return Err(err);
+    }
 
-            if response.status().is_success() {
-                mr_rec.push(("git/pr.patch", patch.as_bytes()));
-            }
+    writer::snapshot(&mut target, ext_item)?;
+    Ok(())
+}
 
-            issue.new_record(Files::from(mr_rec).followed_by(pr.author.as_ref().into()).into_iter(), true).unwrap();
-
-            let treq = graphql::PageableRequest::new(GITHUB_GRAPHQL, &configuration.github.as_ref().unwrap().token,
-                                                     timeline::Query::new(owner, repository, timeline::Kind::PullRequest, pr.number), graphql::MemoryPageHandler::default());
-
-            for item in treq {
-                match item {
-                    timeline::TimelineItem::IssueComment { created_at, body, author, url, .. } => {
-                        issue.new_record(Files::from(vec![(".type/Commented", &b""[..]),
-                                              (".timestamp", created_at.as_bytes()),
-                                              ("text", body.as_bytes()),
-                                              (".imported", url.as_bytes())])
-                                             .followed_by(author.as_ref().into())
-                                             .into_iter(), true).unwrap();
-                    },
-                    timeline::TimelineItem::ClosedEvent { created_at, actor, closer } => {
-                         issue.new_record(Files::from(vec![(".type/Closed", &b""[..]),
-                                               (".timestamp", created_at.as_bytes()),
-                                               (".imported", pr.url.as_bytes())])
-                                              .followed_by(closer.as_ref().into())
-                                              .followed_by(actor.as_ref().into())
-                                              .into_iter(), true).unwrap();
-                    },
-                    timeline::TimelineItem::ReopenedEvent { created_at, actor } => {
-                         issue.new_record(Files::from(vec![(".type/Reopened", &b""[..]),
-                                               (".timestamp", created_at.as_bytes()),
-                                               (".imported", pr.url.as_bytes())])
-                                              .followed_by(actor.as_ref().into())
-                                              .into_iter(), true).unwrap();
-                    },
-                    timeline::TimelineItem::LockedEvent { created_at, actor } => {
-                         issue.new_record(Files::from(vec![(".type/Locked", &b""[..]),
-                                               (".timestamp", created_at.as_bytes()),
-                                               (".imported", pr.url.as_bytes())])
-                                              .followed_by(actor.as_ref().into())
-                                              .into_iter(), true).unwrap();
-                    },
-                    timeline::TimelineItem::UnlockedEvent { created_at, actor } => {
-                         issue.new_record(Files::from(vec![(".type/Unlocked", &b""[..]),
-                                               (".timestamp", created_at.as_bytes()),
-                                               (".imported", pr.url.as_bytes())])
-                                              .followed_by(actor.as_ref().into())
-                                              .into_iter(), true).unwrap();
-
-                    },
-                    timeline::TimelineItem::MergedEvent { created_at, actor } => {
-                         issue.new_record(Files::from(vec![(".type/Closed", &b""[..]),
-                                               (".type/Merged", &b""[..]),
-                                               (".timestamp", created_at.as_bytes()),
-                                               (".imported", pr.url.as_bytes())])
-                                              .followed_by(actor.as_ref().into())
-                                              .into_iter(), true).unwrap();
-                    },
-                    _ => (),
-                }
-            }
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::Read;
+    use tempdir::TempDir;
+    use sit_core::{Item, Record};
+    use sit_core::record::RecordExt;
+    use github::GitHub;
+    use testing::{Server, Route};
+
+    fn routes(second_page: Route) -> Vec<Route> {
+        vec![second_page,
+             Route::new(vec!["issues("], include_str!("../fixtures/github/issues_1.json")),
+             Route::new(vec!["pullRequests("], include_str!("../fixtures/github/pull_requests.json")),
+             Route::new(vec!["milestones("], include_str!("../fixtures/github/milestones.json")),
+             Route::new(vec!["issue1: issue(number: 1)"], include_str!("../fixtures/github/timelines.json")),
+             Route::new(vec!["issue: issue(number: 1)", "after: \"Y3Vyc29yOjE=\""], include_str!("../fixtures/github/timeline_1_rest.json")),
+             Route::new(vec!["issue: issue(number: 1)"], include_str!("../fixtures/github/timeline_1.json")),
+             Route::new(vec!["issue: issue("], include_str!("../fixtures/github/timeline_empty.json"))]
+    }
 
-            progress_bar.inc();
-        }
+    const SECOND_PAGE: &str = "after: \"Y3Vyc29yOjI=\"";
 
-        progress_bar.finish();
+    fn options(resume: bool) -> Options {
+        Options { sync: false, resume, jobs: 2, closer_comments: false, report: None, filter: filter::Filter::default() }
+    }
 
+    /// Types of every record of an item, sorted
+    fn record_types(repo: &sit_core::Repository, name: &str) -> Vec<String> {
+        let item = repo.item(name).expect("item not imported");
+        let mut types: Vec<_> = item.record_iter().unwrap().flat_map(|records| records).map(|record| {
+            let mut types: Vec<_> = record.file_iter().map(|(name, _)| name)
+                .filter(|name| name.starts_with(".type/")).map(|name| name[6..].to_string()).collect();
+            types.sort();
+            types.join(",")
+        }).collect();
+        types.sort();
+        types
+    }
 
-    } else {
-        eprintln!("Unrecognized source provider: {}", source);
-        return -1;
+    /// Hashes of every record of a type, sorted
+    fn hashes(repo: &sit_core::Repository, name: &str, type_: &str) -> Vec<String> {
+        let item = repo.item(name).unwrap();
+        let mut hashes: Vec<_> = item.record_iter().unwrap().flat_map(|records| records)
+            .filter(|record| record.file(&format!(".type/{}", type_)).is_some())
+            .map(|record| record.encoded_hash()).collect();
+        hashes.sort();
+        hashes
+    }
+
+    /// Contents of a file in every record of a type that has it, sorted
+    fn files(repo: &sit_core::Repository, name: &str, type_: &str, file: &str) -> Vec<String> {
+        let item = repo.item(name).unwrap();
+        let mut contents: Vec<_> = item.record_iter().unwrap().flat_map(|records| records)
+            .filter(|record| record.file(&format!(".type/{}", type_)).is_some())
+            .filter_map(|record| record.file(file).map(|mut reader| {
+                let mut contents = String::new();
+                reader.read_to_string(&mut contents).unwrap();
+                contents
+            })).collect();
+        contents.sort();
+        contents
+    }
+
+    /// Texts of every record of a type, sorted by timestamp
+    fn texts(repo: &sit_core::Repository, name: &str, type_: &str) -> Vec<String> {
+        let item = repo.item(name).unwrap();
+        let mut texts: Vec<_> = item.record_iter().unwrap().flat_map(|records| records)
+            .filter(|record| record.file(&format!(".type/{}", type_)).is_some())
+            .map(|record| {
+                let (mut timestamp, mut text) = (String::new(), String::new());
+                record.file(".timestamp").unwrap().read_to_string(&mut timestamp).unwrap();
+                record.file("text").unwrap().read_to_string(&mut text).unwrap();
+                (timestamp, text)
+            }).collect();
+        texts.sort();
+        texts.into_iter().map(|(_, text)| text).collect()
+    }
+
+    fn text(repo: &sit_core::Repository, name: &str, type_: &str) -> String {
+        let item = repo.item(name).unwrap();
+        let record = item.record_iter().unwrap().flat_map(|records| records)
+            .find(|record| record.file(&format!(".type/{}", type_)).is_some()).unwrap();
+        let mut text = String::new();
+        record.file("text").unwrap().read_to_string(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn import_github() {
+        let tmp = TempDir::new("sit-issue-import").unwrap();
+        let repo = sit_core::Repository::new(tmp.path().join(".sit")).unwrap();
+        let server = Server::start(routes(Route::new(vec!["issues(", SECOND_PAGE], include_str!("../fixtures/github/issues_2.json"))));
+        let provider = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        let state_path = sync::State::path(&repo, "github", "octo-org/octoroku");
+        let positions_path = state_path.with_extension("pages.json");
+
+        import(&repo, &provider, &state_path, &positions_path, &options(false)).unwrap();
+
+        assert_eq!(record_types(&repo, "github-issue-1"),
+                   vec!["AssigneesChanged", "Closed", "CommentEdited", "Commented", "DetailsChanged", "DetailsChanged",
+                        "Reacted", "Reacted", "Reacted", "SummaryChanged", "TagsChanged"]);
+        assert_eq!(text(&repo, "github-issue-1", "SummaryChanged"), "Fix the build");
+        // Original texts are followed by their revisions
+        assert_eq!(text(&repo, "github-issue-1", "Commented"), "On it!");
+        assert_eq!(text(&repo, "github-issue-1", "CommentEdited"), "On it");
+        assert_eq!(texts(&repo, "github-issue-1", "DetailsChanged"), vec!["Broken", "It's broken"]);
+        // Reactions given at the same time by different users are kept apart
+        assert_eq!(files(&repo, "github-issue-1", "Reacted", "reaction"), vec!["HEART", "THUMBS_UP", "THUMBS_UP"]);
+        // Reactions and revisions of a comment refer to its record
+        let comment = hashes(&repo, "github-issue-1", "Commented");
+        assert_eq!(comment.len(), 1);
+        assert_eq!(files(&repo, "github-issue-1", "Reacted", "comment"), comment);
+        assert_eq!(files(&repo, "github-issue-1", "CommentEdited", "comment"), comment);
+        assert_eq!(record_types(&repo, "github-issue-2"), vec!["DetailsChanged", "SummaryChanged"]);
+        assert_eq!(record_types(&repo, "github-issue-3"), vec!["DetailsChanged", "SummaryChanged"]);
+        assert!(!positions_path.exists());
+        // Timelines are fetched in one batch, only the one that doesn't fit is paged through
+        assert_eq!(server.count(&["issue1: issue(number: 1)", "issue3: issue(number: 3)"]), 1);
+        assert_eq!(server.count(&["issue: issue("]), 1);
+
+        // Importing again adds nothing new, even if closers are shown as comments now
+        import(&repo, &provider, &state_path, &positions_path, &options(false)).unwrap();
+        assert_eq!(record_types(&repo, "github-issue-1").len(), 11);
+        import(&repo, &provider, &state_path, &positions_path, &Options { closer_comments: true, ..options(false) }).unwrap();
+        assert_eq!(record_types(&repo, "github-issue-1").len(), 11);
+        assert_eq!(files(&repo, "github-issue-1", "Closed", "closer/commit"), vec!["3f8c2a"]);
+    }
+
+    #[test]
+    fn import_github_after_archive() {
+        let tmp = TempDir::new("sit-issue-import").unwrap();
+        let repo = sit_core::Repository::new(tmp.path().join(".sit")).unwrap();
+        let state_path = sync::State::path(&repo, "github", "octo-org/octoroku");
+        let positions_path = state_path.with_extension("pages.json");
+
+        let archive = <archive::GitHubArchive as provider::Provider>::open(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/github-archive"), None).unwrap().unwrap();
+        import(&repo, &archive, &state_path, &positions_path, &options(false)).unwrap();
+        let issue = record_types(&repo, "github-issue-1");
+        let milestone = record_types(&repo, "github-milestone-1");
+        assert!(issue.contains(&"Closed".to_string()) && issue.contains(&"Tagged".to_string()));
+
+        // Syncing with GitHub itself afterwards recognizes what the archive had already
+        let server = Server::start(vec![
+            Route::new(vec!["issues("], include_str!("../fixtures/github/archived_issues.json")),
+            Route::new(vec!["pullRequests("], include_str!("../fixtures/github/pull_requests.json")),
+            Route::new(vec!["milestones("], include_str!("../fixtures/github/archived_milestones.json")),
+            Route::new(vec!["issue: issue(number: 1)"], include_str!("../fixtures/github/archived_timeline.json"))]);
+        let provider = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        import(&repo, &provider, &state_path, &positions_path, &options(false)).unwrap();
+        assert_eq!(server.count(&["issue: issue(number: 1)"]), 1);
+        assert_eq!(record_types(&repo, "github-issue-1"), issue);
+        assert_eq!(record_types(&repo, "github-milestone-1"), milestone);
+    }
+
+    #[test]
+    fn import_single_github() {
+        let tmp = TempDir::new("sit-issue-import").unwrap();
+        let repo = sit_core::Repository::new(tmp.path().join(".sit")).unwrap();
+        let mut routes = routes(Route::new(vec!["pullRequests("], include_str!("../fixtures/github/pull_requests.json")));
+        // After timelines, which are queried with `issue: issue(number: N)` too
+        routes.push(Route::new(vec!["issue(number: 2)"], include_str!("../fixtures/github/issue_2.json")));
+        routes.push(Route::new(vec!["pullRequest(number: 2)"], include_str!("../fixtures/github/errors.json")));
+        routes.insert(0, Route::new(vec!["milestones("], include_str!("../fixtures/github/milestones_2.json")));
+        let server = Server::start(routes);
+        let provider = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        let state_path = sync::State::path(&repo, "github", "octo-org/octoroku");
+        let positions_path = state_path.with_extension("pages.json");
+
+        // Numbers are shared by issues and pull requests, only one of them exists
+        let options = Options { filter: filter::Filter { numbers: (Some(2), Some(2)), ..filter::Filter::default() }, ..options(false) };
+        import(&repo, &provider, &state_path, &positions_path, &options).unwrap();
+        assert_eq!(record_types(&repo, "github-issue-2"), vec!["DetailsChanged", "Milestoned", "SummaryChanged"]);
+        assert!(repo.item("github-issue-1").is_none());
+        // Only the milestone of the issue is brought along
+        assert!(repo.item("github-milestone-2").is_some());
+        assert!(repo.item("github-milestone-1").is_none());
+        assert_eq!(server.count(&["issues("]), 0);
+        // Counting and listing share the fetched issue
+        assert_eq!(server.count(&["issue(number: 2)"]) - server.count(&["issue: issue(number: 2)"]), 1);
+        assert_eq!(server.count(&["issue: issue(number: 2)"]), 1);
+        assert!(!state_path.exists());
+
+        // Unless the kind is known
+        let options = Options { filter: filter::Filter { kind: Some(Kind::MergeRequest), ..options.filter }, ..options };
+        assert!(import(&repo, &provider, &state_path, &positions_path, &options).is_err());
+    }
+
+    #[test]
+    fn resume_timeline_github() {
+        let tmp = TempDir::new("sit-issue-import").unwrap();
+        let repo = sit_core::Repository::new(tmp.path().join(".sit")).unwrap();
+        let state_path = sync::State::path(&repo, "github", "octo-org/octoroku");
+        let positions_path = state_path.with_extension("pages.json");
+        let second_page = || Route::new(vec!["issues(", SECOND_PAGE], include_str!("../fixtures/github/issues_2.json"));
+
+        // The first page of the timeline is imported before the rest fails
+        let mut failing = routes(second_page());
+        failing.insert(0, Route::failing(vec!["issue: issue(number: 1)", "after: \"Y3Vyc29yOjE=\""]));
+        let server = Server::start(failing);
+        let provider = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        assert!(import(&repo, &provider, &state_path, &positions_path, &options(false)).is_err());
+        assert_eq!(files(&repo, "github-issue-1", "Commented", "text"), vec!["On it!"]);
+        assert_eq!(record_types(&repo, "github-issue-1").iter().filter(|t| t.as_str() == "Closed").count(), 0);
+
+        // The timeline is resumed after the entries imported already
+        let server = Server::start(routes(second_page()));
+        let provider = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        import(&repo, &provider, &state_path, &positions_path, &options(true)).unwrap();
+        assert_eq!(record_types(&repo, "github-issue-1"),
+                   vec!["AssigneesChanged", "Closed", "CommentEdited", "Commented", "DetailsChanged", "DetailsChanged",
+                        "Reacted", "Reacted", "Reacted", "SummaryChanged", "TagsChanged"]);
+        assert_eq!(server.count(&["issue1: issue(number: 1)"]), 0);
+        assert_eq!(server.count(&["issue: issue(number: 1)"]), 1);
+        assert!(!positions_path.exists());
+    }
+
+    #[test]
+    fn snapshot() {
+        let tmp = TempDir::new("sit-issue-import").unwrap();
+        let repo = sit_core::Repository::new(tmp.path().join(".sit")).unwrap();
+        let mut ext_item = provider::Item {
+            kind: Kind::Issue,
+            name: "github-issue-1".into(),
+            number: 1,
+            url: "https://github.com/octo-org/octoroku/issues/1".into(),
+            title: "Title".into(),
+            body: "".into(),
+            edits: vec![],
+            reactions: vec![],
+            created_at: "2018-03-01T10:00:00Z".into(),
+            updated_at: "2018-04-01T10:00:00Z".into(),
+            closed: false,
+            author: None,
+            author_login: None,
+            labels: Some(vec![provider::Label { name: "bug".into(), color: Some("ee0701".into()) }]),
+            assignees: Some(vec!["(https://github.com/octocat)".into()]),
+            milestone: Some("github-milestone-1".into()),
+        };
+        let snapshot = |ext_item: &provider::Item| {
+            let mut target = sync::Target::open(&repo, "github-issue-1", None).unwrap();
+            writer::snapshot(&mut target, ext_item).unwrap();
+        };
+
+        snapshot(&ext_item);
+        assert_eq!(record_types(&repo, "github-issue-1"), vec!["AssigneesChanged", "Milestoned", "SummaryChanged", "TagsChanged"]);
+
+        // Updates that don't touch labels, assignees or the milestone aren't recorded
+        ext_item.updated_at = "2018-04-02T10:00:00Z".into();
+        snapshot(&ext_item);
+        assert_eq!(record_types(&repo, "github-issue-1").len(), 4);
+
+        ext_item.updated_at = "2018-04-03T10:00:00Z".into();
+        ext_item.labels = Some(vec![provider::Label { name: "bug".into(), color: Some("fc2929".into()) }]);
+        ext_item.milestone = None;
+        snapshot(&ext_item);
+        assert_eq!(record_types(&repo, "github-issue-1"),
+                   vec!["AssigneesChanged", "Demilestoned", "Milestoned", "SummaryChanged", "TagsChanged", "TagsChanged"]);
     }
 
-    return 0;
+    #[test]
+    fn reopen_milestone() {
+        let tmp = TempDir::new("sit-issue-import").unwrap();
+        let repo = sit_core::Repository::new(tmp.path().join(".sit")).unwrap();
+        let mut milestone = provider::Milestone {
+            name: "github-milestone-1".into(),
+            url: "https://github.com/octo-org/octoroku/milestones/1".into(),
+            title: "v1".into(),
+            description: "".into(),
+            due_on: None,
+            closed: true,
+            created_at: "2018-03-01T10:00:00Z".into(),
+            updated_at: "2018-04-01T10:00:00Z".into(),
+            closed_at: Some("2018-04-01T10:00:00Z".into()),
+            author: None,
+        };
+        let import = |milestone: &provider::Milestone| {
+            let mut target = sync::Target::open(&repo, "github-milestone-1", None).unwrap();
+            writer::milestone(&mut target, milestone).unwrap();
+        };
+        let count = |type_: &str| record_types(&repo, "github-milestone-1").iter().filter(|t| t.as_str() == type_).count();
+
+        import(&milestone);
+        assert_eq!((count("Closed"), count("Reopened")), (1, 0));
+
+        milestone.closed = false;
+        milestone.closed_at = None;
+        milestone.updated_at = "2018-04-02T10:00:00Z".into();
+        import(&milestone);
+        assert_eq!((count("Closed"), count("Reopened")), (1, 1));
+
+        // Later updates of a reopened milestone don't reopen it again
+        milestone.updated_at = "2018-04-03T10:00:00Z".into();
+        import(&milestone);
+        assert_eq!((count("Closed"), count("Reopened")), (1, 1));
+
+        // Unless it was closed in between
+        milestone.closed = true;
+        milestone.closed_at = Some("2018-04-04T10:00:00Z".into());
+        milestone.updated_at = "2018-04-04T10:00:00Z".into();
+        import(&milestone);
+        milestone.closed = false;
+        milestone.closed_at = None;
+        milestone.updated_at = "2018-04-05T10:00:00Z".into();
+        import(&milestone);
+        assert_eq!((count("Closed"), count("Reopened")), (2, 2));
+    }
 
+    #[test]
+    fn count_github() {
+        let server = Server::start(routes(Route::new(vec!["issues(", SECOND_PAGE], include_str!("../fixtures/github/issues_2.json"))));
+        let provider = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        assert_eq!(provider::Provider::count(&provider, Kind::Issue, None, &filter::Filter::default()).unwrap(), 3);
+        assert_eq!(server.count(&["issues(", SECOND_PAGE]), 0);
+
+        // GitHub can't filter by numbers, so its total count would include every issue
+        let filter = filter::Filter { numbers: (Some(2), None), ..filter::Filter::default() };
+        assert_eq!(provider::Provider::count(&provider, Kind::Issue, None, &filter).unwrap(), 2);
+        assert_eq!(server.count(&["issues(", SECOND_PAGE]), 1);
+
+        // Pull requests can't be listed by update time, the listing stops at the first one not updated since
+        let server = Server::start(vec![Route::new(vec!["pullRequests("], include_str!("../fixtures/github/pull_requests_1.json"))]);
+        let provider = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        let filter = filter::Filter::default();
+        assert_eq!(provider::Provider::count(&provider, Kind::MergeRequest, None, &filter).unwrap(), 1);
+        assert_eq!(provider::Provider::count(&provider, Kind::MergeRequest, Some(&"2018-05-01T00:00:00Z".into()), &filter).unwrap(), 0);
+    }
+
+    #[test]
+    fn resume_github() {
+        let tmp = TempDir::new("sit-issue-import").unwrap();
+        let repo = sit_core::Repository::new(tmp.path().join(".sit")).unwrap();
+        let state_path = sync::State::path(&repo, "github", "octo-org/octoroku");
+        let positions_path = state_path.with_extension("pages.json");
+
+        let server = Server::start(routes(Route::failing(vec!["issues(", SECOND_PAGE])));
+        let provider = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        assert!(import(&repo, &provider, &state_path, &positions_path, &options(false)).is_err());
+        let imported = (record_types(&repo, "github-issue-1"), record_types(&repo, "github-issue-2"));
+        assert!(repo.item("github-issue-3").is_none());
+        assert!(positions_path.exists());
+
+        let server = Server::start(routes(Route::new(vec!["issues(", SECOND_PAGE], include_str!("../fixtures/github/issues_2.json"))));
+        let provider = GitHub::new(server.url.as_str(), "octo-org", "octoroku");
+        import(&repo, &provider, &state_path, &positions_path, &options(true)).unwrap();
+        assert_eq!(record_types(&repo, "github-issue-3"), vec!["DetailsChanged", "SummaryChanged"]);
+        // Only the count went through the first page, and completed issues were not revisited
+        assert_eq!(server.count(&["issues("]), 2);
+        assert_eq!(server.count(&["issues(", SECOND_PAGE]), 1);
+        assert_eq!(server.count(&["(number: 1)"]), 0);
+        assert_eq!(server.count(&["(number: 2)"]), 0);
+        assert_eq!((record_types(&repo, "github-issue-1"), record_types(&repo, "github-issue-2")), imported);
+        assert!(!positions_path.exists());
+    }
 
 }
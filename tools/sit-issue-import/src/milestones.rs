@@ -0,0 +1,86 @@
+use std::fmt::Display;
+use askama::Template;
+use graphql::{Query as GraphQLQuery, Pageable, HasPageInfo, HasTotalCount, PageInfo, Paged};
+use actor::Actor;
+
+#[derive(Template)]
+#[template(path = "milestones.graphql")]
+pub struct Query<S> where S : AsRef<str> + Display {
+    pub owner: S,
+    pub repository: S,
+    pub after: Option<String>,
+}
+
+impl<S> Query<S> where S : AsRef<str> + Display {
+    pub fn new(owner: S, repository: S) -> Self {
+        Query { owner, repository, after: None }
+    }
+}
+
+impl<S> GraphQLQuery for Query<S> where S : AsRef<str> + Display + Copy {
+    type Result = Response;
+    fn query(&self) -> String {
+        self.render().unwrap()
+    }
+}
+
+impl<S> Pageable for Query<S> where S : AsRef<str> + Display + Copy {
+    type Item = Milestone;
+    fn after(&self, cursor: String) -> Self {
+        Query { owner: self.owner, repository: self.repository, after: Some(cursor) }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Milestone {
+    pub number: usize,
+    pub url: String,
+    pub state: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub due_on: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub closed_at: Option<String>,
+    pub creator: Option<Actor>,
+}
+
+/// Milestone an issue or a pull request belongs to
+#[derive(Deserialize, Debug)]
+pub struct MilestoneRef {
+    pub number: usize,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MilestoneNode {
+    milestones: Paged<Milestone>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    repository: MilestoneNode,
+}
+
+impl HasPageInfo for Response {
+    fn page_info(&self) -> &PageInfo {
+        &self.repository.milestones.page_info
+    }
+}
+
+impl HasTotalCount for Response {
+    fn total_count(&self) -> usize {
+        self.repository.milestones.total_count
+    }
+}
+
+impl IntoIterator for Response {
+    type Item = Milestone;
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.repository.milestones.into_iter()
+    }
+}
@@ -0,0 +1,195 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use git2;
+
+use error::Error;
+
+/// Where the issue-tracking module is installed from unless told otherwise
+pub const DEFAULT_SOURCE: &str = "https://github.com/sit-fyi/issue-tracking.git";
+
+/// How the module ended up in place
+#[derive(Debug, PartialEq)]
+pub enum Installation {
+    /// It was there already and was left as is
+    Existing,
+    /// It was there already and the requested revision was checked out in it
+    CheckedOut,
+    Cloned,
+    Copied,
+}
+
+/// Installs the issue-tracking module into `path` unless it is already there
+///
+/// `source` is a URL or a path of a git repository to clone (and check out
+/// `revision` from, if given), or a path of a plain directory to copy.
+///
+/// If the module is already there, `revision` is checked out in it, which
+/// is only possible if it is a git checkout. Local changes to it are only
+/// discarded if `force` is set, otherwise they make the installation fail.
+pub fn install(source: &str, revision: Option<&str>, path: &Path, force: bool) -> Result<Installation, Error> {
+    if path.exists() {
+        return match revision {
+            None => Ok(Installation::Existing),
+            Some(revision) => {
+                let repository = git2::Repository::open(path)
+                    .map_err(|_| Error::Module(format!("{} is not a git checkout, revision {} can't be checked out in it", path.display(), revision)))?;
+                if !force && modified(&repository).map_err(|err| Error::Module(format!("can't check {}: {}", path.display(), err.message())))? {
+                    return Err(Error::Module(format!("{} has local changes that checking out {} would discard, \
+                                                  use --force-module-checkout to discard them anyway", path.display(), revision)));
+                }
+                checkout(&repository, revision, true, force)
+                    .map_err(|err| Error::Module(format!("can't check out {} in {}: {}", revision, path.display(), err.message())))?;
+                Ok(Installation::CheckedOut)
+            },
+        };
+    }
+    let local = Path::new(source);
+    if local.is_dir() && !local.join(".git").exists() && !local.join("HEAD").is_file() {
+        if revision.is_some() {
+            return Err(Error::Module(format!("{} is not a git repository, a revision can't be checked out from it", source)));
+        }
+        copy(local, path).map_err(|err| Error::Module(format!("can't copy {}: {}", source, err)))?;
+        return Ok(Installation::Copied);
+    }
+    clone(source, revision, path).map_err(|err| {
+        // Don't leave a partial clone behind, it would be mistaken for an installed module next time
+        let _ = fs::remove_dir_all(path);
+        Error::Module(format!("can't clone {}: {}", source, err.message()))
+    })?;
+    Ok(Installation::Cloned)
+}
+
+fn clone(source: &str, revision: Option<&str>, path: &Path) -> Result<(), git2::Error> {
+    let repository = git2::Repository::clone(source, path)?;
+    if let Some(revision) = revision {
+        checkout(&repository, revision, false, false)?;
+    }
+    Ok(())
+}
+
+/// Returns `true` if tracked files of the checkout were changed
+fn modified(repository: &git2::Repository) -> Result<bool, git2::Error> {
+    let statuses = repository.statuses(Some(git2::StatusOptions::new().include_untracked(false).include_ignored(false)))?;
+    Ok(!statuses.is_empty())
+}
+
+fn checkout(repository: &git2::Repository, revision: &str, fetch: bool, force: bool) -> Result<(), git2::Error> {
+    let object = match repository.revparse_single(revision) {
+        Ok(object) => object,
+        // The revision may be newer than the checkout
+        Err(_) if fetch => {
+            repository.find_remote("origin")?
+                .fetch(&["+refs/heads/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"], None, None)?;
+            repository.revparse_single(revision)
+                .or_else(|_| repository.revparse_single(&format!("origin/{}", revision)))?
+        },
+        Err(err) => return Err(err),
+    };
+    let mut options = git2::build::CheckoutBuilder::new();
+    if force {
+        options.force();
+    } else {
+        options.safe();
+    }
+    repository.checkout_tree(&object, Some(&mut options))?;
+    repository.set_head_detached(object.peel_to_commit()?.id())
+}
+
+fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::{Read, Write};
+    use tempdir::TempDir;
+
+    #[test]
+    fn local_copy() {
+        let tmp = TempDir::new("sit-import").unwrap();
+        let source = tmp.path().join("issue-tracking");
+        fs::create_dir_all(source.join("reducers")).unwrap();
+        fs::File::create(source.join("reducers").join("Status.js")).unwrap().write_all(b"module.exports = {}").unwrap();
+        let target = tmp.path().join("modules").join("issue-tracking");
+
+        assert_eq!(install(source.to_str().unwrap(), None, &target, false).unwrap(), Installation::Copied);
+        let mut reducer = String::new();
+        fs::File::open(target.join("reducers").join("Status.js")).unwrap().read_to_string(&mut reducer).unwrap();
+        assert_eq!(reducer, "module.exports = {}");
+
+        assert_eq!(install("https://example.com/unreachable.git", None, &target, false).unwrap(), Installation::Existing);
+    }
+
+    #[test]
+    fn revision_of_existing_checkout() {
+        let tmp = TempDir::new("sit-import").unwrap();
+        let source = tmp.path().join("source");
+        let repository = git2::Repository::init(&source).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let mut parents = vec![];
+        for version in &["v1", "v2"] {
+            fs::File::create(source.join("VERSION")).unwrap().write_all(version.as_bytes()).unwrap();
+            let mut index = repository.index().unwrap();
+            index.add_path(Path::new("VERSION")).unwrap();
+            let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent_commits = parents.iter().map(|id| repository.find_commit(*id).unwrap()).collect::<Vec<_>>();
+            let commit = repository.commit(Some("HEAD"), &signature, &signature, version, &tree,
+                                           &parent_commits.iter().collect::<Vec<_>>()).unwrap();
+            repository.tag_lightweight(version, &repository.find_object(commit, None).unwrap(), false).unwrap();
+            parents = vec![commit];
+        }
+        let target = tmp.path().join("issue-tracking");
+        let version = || {
+            let mut version = String::new();
+            fs::File::open(target.join("VERSION")).unwrap().read_to_string(&mut version).unwrap();
+            version
+        };
+
+        assert_eq!(install(source.to_str().unwrap(), None, &target, false).unwrap(), Installation::Cloned);
+        assert_eq!(version(), "v2");
+        assert_eq!(install(source.to_str().unwrap(), Some("v1"), &target, false).unwrap(), Installation::CheckedOut);
+        assert_eq!(version(), "v1");
+        assert!(install(source.to_str().unwrap(), Some("v3"), &target, false).is_err());
+
+        // Local changes are kept unless discarding them is asked for
+        fs::File::create(target.join("VERSION")).unwrap().write_all(b"patched").unwrap();
+        match install(source.to_str().unwrap(), Some("v2"), &target, false) {
+            Err(Error::Module(message)) => assert!(message.contains("local changes"), "unexpected error {}", message),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(version(), "patched");
+        assert_eq!(install(source.to_str().unwrap(), Some("v2"), &target, true).unwrap(), Installation::CheckedOut);
+        assert_eq!(version(), "v2");
+    }
+
+    #[test]
+    fn revision_of_existing_copy() {
+        let tmp = TempDir::new("sit-import").unwrap();
+        let target = tmp.path().join("issue-tracking");
+        fs::create_dir_all(&target).unwrap();
+        assert!(install(DEFAULT_SOURCE, Some("v1"), &target, false).is_err());
+    }
+
+    #[test]
+    fn revision_of_plain_directory() {
+        let tmp = TempDir::new("sit-import").unwrap();
+        let target = tmp.path().join("issue-tracking");
+        assert!(install(tmp.path().to_str().unwrap(), Some("v1"), &target, false).is_err());
+        assert!(!target.exists());
+    }
+
+}
@@ -0,0 +1,212 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, mpsc};
+
+use crossbeam;
+
+/// `work` panicked, with the panic's message if it had one
+#[derive(Debug, PartialEq)]
+pub struct WorkerPanic(pub Option<String>);
+
+impl WorkerPanic {
+    fn new(payload: Box<Any + Send>) -> Self {
+        match payload.downcast::<String>() {
+            Ok(message) => WorkerPanic(Some(*message)),
+            Err(payload) => WorkerPanic(payload.downcast_ref::<&str>().map(|message| message.to_string())),
+        }
+    }
+}
+
+/// Runs `work` on every input in up to `jobs` threads, handing the results
+/// to `done` in the order of the inputs
+///
+/// At most twice as many inputs as there are threads are taken ahead of the
+/// one handed to `done`. After an error (either taken from `inputs` or returned
+/// by `done`), no more inputs are taken. Inputs already taken before an error
+/// in `inputs` are still handed to `done`.
+///
+/// If `work` panics, the panic is returned as an error in place of handing
+/// that input to `done`.
+pub fn ordered<I, T, R, E, W, D>(jobs: usize, inputs: I, work: W, mut done: D) -> Result<(), E>
+    where I: Iterator<Item = Result<T, E>>, T: Send, R: Send, E: From<WorkerPanic>,
+          W: Fn(&T) -> R + Sync, D: FnMut(T, R) -> Result<(), E> {
+    let jobs = if jobs == 0 { 1 } else { jobs };
+    let (input_tx, input_rx) = mpsc::channel::<(usize, T)>();
+    let input_rx = Arc::new(Mutex::new(input_rx));
+    let (result_tx, result_rx) = mpsc::channel::<(usize, T, Result<R, WorkerPanic>)>();
+    let work = &work;
+
+    crossbeam::scope(move |scope| {
+        for _ in 0..jobs {
+            let input_rx = input_rx.clone();
+            let result_tx = result_tx.clone();
+            scope.spawn(move || loop {
+                let input = input_rx.lock().unwrap().recv();
+                match input {
+                    Ok((index, input)) => {
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| work(&input))).map_err(WorkerPanic::new);
+                        if result_tx.send((index, input, result)).is_err() {
+                            break;
+                        }
+                    },
+                    // No more inputs
+                    Err(_) => break,
+                }
+            });
+        }
+        // Receiving fails rather than blocking forever once all workers are gone
+        drop(result_tx);
+
+        let mut inputs = inputs;
+        let mut outcome = Ok(());
+        // An error taken from `inputs` is only returned once everything before it is done
+        let mut failure = None;
+        let mut exhausted = false;
+        let (mut taken, mut handed) = (0, 0);
+        let mut ready = BTreeMap::new();
+        loop {
+            while !exhausted && outcome.is_ok() && taken - handed < 2 * jobs {
+                match inputs.next() {
+                    Some(Ok(input)) => {
+                        input_tx.send((taken, input)).unwrap();
+                        taken += 1;
+                    },
+                    Some(Err(err)) => {
+                        failure = Some(err);
+                        exhausted = true;
+                    },
+                    None => exhausted = true,
+                }
+            }
+            if handed == taken {
+                break;
+            }
+            // Every worker sends a result for each input it takes, even if `work` panics
+            let (index, input, result) = result_rx.recv().unwrap();
+            ready.insert(index, (input, result));
+            while let Some((input, result)) = ready.remove(&handed) {
+                handed += 1;
+                if outcome.is_ok() {
+                    outcome = result.map_err(E::from).and_then(|result| done(input, result));
+                }
+            }
+        }
+        // Lets the workers finish
+        drop(input_tx);
+        match (failure, outcome) {
+            (Some(err), Ok(_)) => Err(err),
+            (_, outcome) => outcome,
+        }
+    })
+}
+
+/// Groups inputs into batches of up to `size`
+///
+/// An error ends the batch being gathered, which is handed out before the error.
+pub fn batches<I, T, E>(inputs: I, size: usize) -> Batches<I, E> where I: Iterator<Item = Result<T, E>> {
+    Batches { inputs, size: if size == 0 { 1 } else { size }, failure: None }
+}
+
+pub struct Batches<I, E> {
+    inputs: I,
+    size: usize,
+    failure: Option<E>,
+}
+
+impl<I, T, E> Iterator for Batches<I, E> where I: Iterator<Item = Result<T, E>> {
+    type Item = Result<Vec<T>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.failure.take() {
+            return Some(Err(err));
+        }
+        let mut batch = vec![];
+        while batch.len() < self.size {
+            match self.inputs.next() {
+                Some(Ok(input)) => batch.push(input),
+                Some(Err(err)) => {
+                    if batch.is_empty() {
+                        return Some(Err(err));
+                    }
+                    self.failure = Some(err);
+                    break;
+                },
+                None => break,
+            }
+        }
+        if batch.is_empty() { None } else { Some(Ok(batch)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq)]
+    enum Failure {
+        Input,
+        Done,
+        Panic(Option<String>),
+    }
+
+    impl From<WorkerPanic> for Failure {
+        fn from(panic: WorkerPanic) -> Self {
+            Failure::Panic(panic.0)
+        }
+    }
+
+    // Takes a reference like any `work` does
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn sleepy(n: &u64) -> u64 {
+        // Later inputs finish first
+        thread::sleep(Duration::from_millis(50 - n * 10));
+        n * 2
+    }
+
+    #[test]
+    fn in_order() {
+        let mut results = vec![];
+        ordered(3, (0..5).map(Ok::<u64, Failure>), sleepy, |n, doubled| {
+            results.push((n, doubled));
+            Ok(())
+        }).unwrap();
+        assert_eq!(results, vec![(0, 0), (1, 2), (2, 4), (3, 6), (4, 8)]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut results = vec![];
+        let inputs = (0..5).map(|n| if n == 3 { Err(Failure::Input) } else { Ok(n) });
+        assert_eq!(ordered(2, inputs, sleepy, |n, _| { results.push(n); Ok(()) }), Err(Failure::Input));
+        assert_eq!(results, vec![0, 1, 2]);
+
+        let mut results = vec![];
+        assert_eq!(ordered(2, (0..5).map(Ok), sleepy, |n, _| {
+            results.push(n);
+            if n == 1 { Err(Failure::Done) } else { Ok(()) }
+        }), Err(Failure::Done));
+        assert_eq!(results, vec![0, 1]);
+    }
+
+    #[test]
+    fn panics() {
+        let mut results = vec![];
+        assert_eq!(ordered(2, (0..5).map(Ok), |n| if *n == 2 { panic!("worker {}", n) } else { sleepy(n) }, |n, _| {
+            results.push(n);
+            Ok(())
+        }), Err(Failure::Panic(Some("worker 2".into()))));
+        assert_eq!(results, vec![0, 1]);
+    }
+
+    #[test]
+    fn batching() {
+        let inputs = (0..7).map(|n| if n == 5 { Err("input") } else { Ok(n) });
+        assert_eq!(batches(inputs, 2).collect::<Vec<_>>(),
+                   vec![Ok(vec![0, 1]), Ok(vec![2, 3]), Ok(vec![4]), Err("input"), Ok(vec![6])]);
+    }
+
+}
@@ -0,0 +1,360 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use graphql::{PageHandler, Position, SharedPageHandler};
+use error::Error;
+use filter::Filter;
+use backoff::Backoff;
+use {github, gitlab, archive};
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub github: Option<github::Config>,
+    pub gitlab: Option<gitlab::Config>,
+    /// File the configuration was read from
+    #[serde(skip)]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Kind {
+    Issue,
+    MergeRequest,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Label {
+    pub name: String,
+    /// Hex color code, without `#`
+    pub color: Option<String>,
+}
+
+/// Revision of the text of an issue, a merge request or a comment
+#[derive(Clone, PartialEq, Debug)]
+pub struct Edit {
+    pub edited_at: String,
+    pub editor: Option<String>,
+    /// Text as of this revision
+    pub text: String,
+}
+
+/// What closed an issue or a merge request
+#[derive(Clone, PartialEq, Debug)]
+pub enum Closer {
+    /// Object ID of the commit
+    Commit(String),
+    MergeRequest {
+        url: String,
+        /// Name of the SIT item of the merge request, unless it's in another repository
+        item: Option<String>,
+    },
+}
+
+impl fmt::Display for Closer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Closer::Commit(ref oid) => write!(f, "Closed with {}", oid),
+            &Closer::MergeRequest { item: Some(ref item), .. } => write!(f, "Closed with {}", item),
+            &Closer::MergeRequest { ref url, .. } => write!(f, "Closed with {}", url),
+        }
+    }
+}
+
+/// Reaction of a user, such as a thumbs-up
+#[derive(Clone, Debug)]
+pub struct Reaction {
+    /// Uniquely identifies the reaction within the source
+    pub url: String,
+    pub created_at: String,
+    pub author: Option<String>,
+    /// Kind of the reaction, as named by the source
+    pub content: String,
+}
+
+/// Issue or merge request
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub kind: Kind,
+    /// Name of the SIT item to import into
+    pub name: String,
+    pub number: usize,
+    pub url: String,
+    pub title: String,
+    /// Original body
+    pub body: String,
+    /// Later revisions of the body, oldest first
+    pub edits: Vec<Edit>,
+    pub reactions: Vec<Reaction>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Closed or merged
+    pub closed: bool,
+    pub author: Option<String>,
+    /// Login or username of the author
+    pub author_login: Option<String>,
+    /// Labels currently set, if the source reports them
+    pub labels: Option<Vec<Label>>,
+    /// Users currently assigned, if the source reports them
+    pub assignees: Option<Vec<String>>,
+    /// Name of the SIT item of the milestone currently set
+    pub milestone: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Milestone {
+    /// Name of the SIT item to import into
+    pub name: String,
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    pub due_on: Option<String>,
+    pub closed: bool,
+    pub created_at: String,
+    pub updated_at: String,
+    pub closed_at: Option<String>,
+    pub author: Option<String>,
+}
+
+/// An entry in the history of an issue or a merge request
+#[derive(Clone, Debug)]
+pub enum Event {
+    Commented {
+        url: String,
+        /// Original text
+        body: String,
+        created_at: String,
+        author: Option<String>,
+        /// Later revisions of the text, oldest first
+        edits: Vec<Edit>,
+        reactions: Vec<Reaction>,
+    },
+    Assigned {
+        /// Uniquely identifies the event within the source
+        url: String,
+        created_at: String,
+        actor: Option<String>,
+        assignee: String,
+    },
+    Unassigned {
+        /// Uniquely identifies the event within the source
+        url: String,
+        created_at: String,
+        actor: Option<String>,
+        assignee: String,
+    },
+    Reviewed {
+        url: String,
+        created_at: String,
+        author: Option<String>,
+        /// `APPROVED`, `CHANGES_REQUESTED`, `COMMENTED` or `DISMISSED`
+        state: String,
+        body: String,
+    },
+    /// Inline comment of a review
+    ReviewCommented {
+        url: String,
+        created_at: String,
+        author: Option<String>,
+        body: String,
+        /// URL of the review, if the comment belongs to one
+        review: Option<String>,
+        path: String,
+        /// Position within the diff of the commit the comment was made on
+        position: usize,
+        diff_hunk: String,
+        commit: Option<String>,
+        /// URL of the comment this one replies to
+        in_reply_to: Option<String>,
+    },
+    Renamed {
+        /// Uniquely identifies the event within the source
+        url: String,
+        created_at: String,
+        actor: Option<String>,
+        previous_title: String,
+        current_title: String,
+    },
+    Milestoned {
+        /// Uniquely identifies the event within the source
+        url: String,
+        created_at: String,
+        actor: Option<String>,
+        title: String,
+        /// Name of the SIT item of the milestone, unless it no longer exists
+        milestone: Option<String>,
+    },
+    Demilestoned {
+        /// Uniquely identifies the event within the source
+        url: String,
+        created_at: String,
+        actor: Option<String>,
+        title: String,
+        /// Name of the SIT item of the milestone, unless it no longer exists
+        milestone: Option<String>,
+    },
+    Labeled {
+        /// Uniquely identifies the event within the source
+        url: String,
+        created_at: String,
+        actor: Option<String>,
+        label: Label,
+    },
+    Unlabeled {
+        /// Uniquely identifies the event within the source
+        url: String,
+        created_at: String,
+        actor: Option<String>,
+        label: Label,
+    },
+    Closed {
+        created_at: String,
+        actor: Option<String>,
+        closer: Option<Closer>,
+    },
+    Reopened {
+        created_at: String,
+        actor: Option<String>,
+    },
+    Locked {
+        created_at: String,
+        actor: Option<String>,
+    },
+    Unlocked {
+        created_at: String,
+        actor: Option<String>,
+    },
+    Merged {
+        created_at: String,
+        actor: Option<String>,
+    },
+    /// Reference to the issue or merge request from elsewhere, or the other way around
+    Linked {
+        /// Uniquely identifies the event within the source
+        url: String,
+        created_at: String,
+        actor: Option<String>,
+        /// `cross-referenced` (mentioned in the target), `referenced` (mentioned in a commit)
+        /// or `closed-by` (closed by the target merge request)
+        relation: String,
+        /// Name of the SIT item of the target, if it's imported from the same repository
+        item: Option<String>,
+        /// Web URL of the target
+        target: String,
+        commit: Option<String>,
+        /// Describes a target in another repository
+        text: Option<String>,
+    },
+    /// Event that has no SIT counterpart
+    Skipped {
+        /// Type of the event, as named by the source
+        event: String,
+    },
+}
+
+/// Timelines are fetched from several threads at once, so providers have to be `Sync`
+pub trait Provider: Sync {
+    /// Recognizes the source and authenticates using the configuration
+    ///
+    /// Returns `None` if the source is not recognized.
+    fn open(source: &str, config: Option<&Config>) -> Option<Result<Self, String>> where Self: Sized;
+    /// Name of the provider, used to keep the import state apart
+    fn name(&self) -> &str;
+    /// Path of the source repository within the provider
+    fn path(&self) -> &str;
+    /// The only issue or merge request to import, if the source points to one
+    fn selection(&self) -> Option<(Kind, usize)> {
+        None
+    }
+    /// Returns the number of issues or merge requests updated since a given time that match the filter
+    ///
+    /// Sources that can't apply the whole filter themselves count with [`count`].
+    fn count(&self, kind: Kind, since: Option<&String>, filter: &Filter) -> Result<usize, Error>;
+    /// Lists issues or merge requests updated since a given time
+    ///
+    /// The filter is applied as far as the source supports it, items that don't match it may still be listed.
+    fn items<'a>(&'a self, kind: Kind, since: Option<&String>, filter: &Filter, page_handler: Box<PageHandler>) -> Box<Iterator<Item = Result<Item, Error>> + 'a>;
+    /// Lists all milestones
+    fn milestones<'a>(&'a self) -> Box<Iterator<Item = Result<Milestone, Error>> + 'a> {
+        Box::new(::std::iter::empty())
+    }
+    /// Lists the history of an issue or a merge request, in chronological order
+    fn timeline<'a>(&'a self, item: &Item, page_handler: Box<PageHandler>) -> Box<Iterator<Item = Result<Event, Error>> + 'a>;
+    /// Fetches the histories of several issues or merge requests of the same kind,
+    /// each from its own position
+    ///
+    /// Sources that can fetch several of them in one request override this.
+    fn timelines(&self, items: &[(&Item, Position)]) -> Vec<Timeline> {
+        items.iter().map(|&(item, ref position)| Timeline::fetch(self, item, position.clone())).collect()
+    }
+    /// How many issues or merge requests are best passed to `timelines` at once
+    fn timeline_batch(&self) -> usize {
+        1
+    }
+    /// Retrieves the patch of a merge request
+    ///
+    /// It's formatted by `git format-patch` if the source offers that, and a plain diff otherwise.
+    /// Returns the name of the record file to store it in along with the patch, or `None`
+    /// if the source has no patch for it.
+    fn patch(&self, item: &Item) -> Result<Option<(&'static str, String)>, Error>;
+    /// Rate limiting of the requests to the source, if the provider does any
+    fn backoff(&self) -> Option<&Backoff> {
+        None
+    }
+}
+
+/// History of an issue or a merge request, as far as it could be fetched
+pub struct Timeline {
+    /// Events paired with the position to resume the history from once they are imported
+    pub events: Vec<(Event, Position)>,
+    /// Why the rest of the history couldn't be fetched
+    pub error: Option<Error>,
+}
+
+impl Timeline {
+
+    /// Takes events up to the first error
+    pub fn collect<I: Iterator<Item = Result<(Event, Position), Error>>>(events: I) -> Self {
+        let mut timeline = Timeline { events: vec![], error: None };
+        for event in events {
+            match event {
+                Ok(event) => timeline.events.push(event),
+                Err(err) => {
+                    timeline.error = Some(err);
+                    break;
+                },
+            }
+        }
+        timeline
+    }
+
+    /// Fetches the history of an item from a position
+    pub fn fetch<P: Provider + ?Sized>(provider: &P, item: &Item, position: Position) -> Self {
+        let page_handler = SharedPageHandler::new(position);
+        // Several events made of the same entry are handed out before the request moves on,
+        // so they share the position after that entry
+        Timeline::collect(provider.timeline(item, Box::new(page_handler.clone()))
+                          .map(|event| event.map(|event| (event, page_handler.position()))))
+    }
+
+}
+
+/// Counts listed items that match the filter
+pub fn count<I: Iterator<Item = Result<Item, Error>>>(items: I, filter: &Filter) -> Result<usize, Error> {
+    let mut count = 0;
+    for item in items {
+        if filter.matches(&item?) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Finds a provider that recognizes the source
+pub fn open(source: &str, config: Option<&Config>) -> Option<Result<Box<Provider>, String>> {
+    fn boxed<P: Provider + 'static>(provider: Result<P, String>) -> Result<Box<Provider>, String> {
+        provider.map(|p| Box::new(p) as Box<Provider>)
+    }
+    github::GitHub::open(source, config).map(boxed)
+        .or_else(|| gitlab::GitLab::open(source, config).map(boxed))
+        .or_else(|| archive::GitHubArchive::open(source, config).map(boxed))
+}
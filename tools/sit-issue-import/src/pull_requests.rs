@@ -1,7 +1,13 @@
 use std::fmt::Display;
 use askama::Template;
-use graphql::{Query as GraphQLQuery, Pageable, HasPageInfo, HasTotalCount, PageInfo, Paged};
+use graphql::{Query as GraphQLQuery, Pageable, HasPageInfo, HasTotalCount, PageInfo, Paged, Nodes, Connection};
+use provider::Label;
+use milestones::MilestoneRef;
 use author::Author;
+use edits::UserContentEdit;
+use reactions::Reaction;
+use filter::Filter;
+use serde_json;
 
 #[derive(Template)]
 #[template(path = "pull_requests.graphql")]
@@ -9,11 +15,31 @@ pub struct Query<S> where S : AsRef<str> + Display {
     pub owner: S,
     pub repository: S,
     pub after: Option<String>,
+    pub since: Option<String>,
+    /// GraphQL list of states to include
+    pub states: Option<String>,
+    /// GraphQL list of labels, any of which has to be set
+    pub labels: Option<String>,
 }
 
 impl<S> Query<S> where S : AsRef<str> + Display {
     pub fn new(owner: S, repository: S) -> Self {
-        Query { owner, repository, after: None }
+        Query { owner, repository, after: None, since: None, states: None, labels: None }
+    }
+
+    pub fn since(self, since: Option<String>) -> Self {
+        Query { since, ..self }
+    }
+
+    /// Narrows down the query to what GitHub can filter by itself
+    pub fn filter(self, filter: &Filter) -> Self {
+        let states = match filter.closed {
+            None => None,
+            Some(false) => Some("[OPEN]"),
+            Some(true) => Some("[CLOSED, MERGED]"),
+        };
+        let labels = if filter.labels.is_empty() { None } else { serde_json::to_string(&filter.labels).ok() };
+        Query { states: states.map(String::from), labels, ..self }
     }
 
 }
@@ -28,13 +54,46 @@ impl<S> GraphQLQuery for Query<S> where S : AsRef<str> + Display + Copy {
 impl<S> Pageable for Query<S> where S : AsRef<str> + Display + Copy {
     type Item = PullRequest;
     fn after(&self, cursor: String) -> Self {
-        Query{ owner: self.owner, repository: self.repository, after: Some(cursor) }
+        Query{ owner: self.owner, repository: self.repository, after: Some(cursor), since: self.since.clone(),
+              states: self.states.clone(), labels: self.labels.clone() }
+    }
+}
+
+/// Query for a single pull request by its number
+#[derive(Template)]
+#[template(path = "pull_request.graphql")]
+pub struct ItemQuery<S> where S : AsRef<str> + Display {
+    pub owner: S,
+    pub repository: S,
+    pub number: usize,
+}
+
+impl<S> ItemQuery<S> where S : AsRef<str> + Display {
+    pub fn new(owner: S, repository: S, number: usize) -> Self {
+        ItemQuery { owner, repository, number }
+    }
+}
+
+impl<S> GraphQLQuery for ItemQuery<S> where S : AsRef<str> + Display + Copy {
+    type Result = ItemResponse;
+    fn query(&self) -> String {
+        self.render().unwrap()
+    }
+}
+
+/// There's only one page
+impl<S> Pageable for ItemQuery<S> where S : AsRef<str> + Display + Copy {
+    type Item = PullRequest;
+    fn after(&self, _cursor: String) -> Self {
+        ItemQuery { owner: self.owner, repository: self.repository, number: self.number }
     }
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PullRequest {
+    /// Node ID
+    pub id: String,
     pub number: usize,
     pub url: String,
     pub state: String,
@@ -44,6 +103,11 @@ pub struct PullRequest {
     pub updated_at: String,
     pub closed_at: Option<String>,
     pub author: Option<Author>,
+    pub user_content_edits: Option<Connection<UserContentEdit>>,
+    pub reactions: Option<Connection<Reaction>>,
+    pub milestone: Option<MilestoneRef>,
+    pub assignees: Nodes<Author>,
+    pub labels: Nodes<Label>,
     pub merged: bool,
     pub merged_at: Option<String>,
 }
@@ -81,3 +145,58 @@ impl IntoIterator for Response {
         self.repository.pull_requests.into_iter()
     }
 }
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequestItemNode {
+    pull_request: PullRequest,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemResponse {
+    repository: PullRequestItemNode,
+    #[serde(skip)]
+    page_info: PageInfo,
+}
+
+impl HasPageInfo for ItemResponse {
+    fn page_info(&self) -> &PageInfo {
+        &self.page_info
+    }
+}
+
+impl HasTotalCount for ItemResponse {
+    fn total_count(&self) -> usize {
+        1
+    }
+}
+
+impl IntoIterator for ItemResponse {
+    type Item = PullRequest;
+    type IntoIter = ::std::option::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Some(self.repository.pull_request).into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn deserialize() {
+        let page: serde_json::Value = serde_json::from_str(include_str!("../fixtures/github/pull_requests_1.json")).unwrap();
+        let response: Response = serde_json::from_value(page["data"].clone()).unwrap();
+        assert_eq!(response.total_count(), 1);
+        assert!(!response.page_info().has_next_page);
+        let prs: Vec<_> = response.into_iter().collect();
+        assert_eq!(prs[0].number, 4);
+        assert!(prs[0].merged);
+        assert_eq!(prs[0].milestone.as_ref().map(|m| m.number), Some(1));
+    }
+
+}
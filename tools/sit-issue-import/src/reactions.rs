@@ -0,0 +1,26 @@
+use author::Author;
+use graphql::Connection;
+use provider;
+
+/// Reaction of a user to an issue, a pull request or a comment
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Reaction {
+    pub id: String,
+    /// `THUMBS_UP`, `THUMBS_DOWN`, `LAUGH`, `HOORAY`, `CONFUSED`, `HEART`, `ROCKET` or `EYES`
+    pub content: String,
+    pub created_at: String,
+    pub user: Option<Author>,
+}
+
+/// Converts reactions to what they were given to, found at `url`
+pub fn reactions(url: &str, reactions: Option<Connection<Reaction>>) -> Vec<provider::Reaction> {
+    reactions.map(|reactions| reactions.nodes).unwrap_or_default().into_iter()
+        .map(|reaction| provider::Reaction {
+            url: format!("{}#reaction-{}", url, reaction.id),
+            author: reaction.user.as_ref().map(Into::into),
+            content: reaction.content,
+            created_at: reaction.created_at,
+        })
+        .collect()
+}
@@ -0,0 +1,277 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io::{self, Read, Cursor};
+use std::collections::{HashSet, HashMap};
+use std::sync::{Arc, Mutex};
+
+use serde_json;
+
+use sit_core::{self, Item, Record};
+use sit_core::record::RecordExt;
+
+use files::Files;
+use dry_run::Report;
+
+/// High-water marks of a previous import from a particular source
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    pub issues: Option<String>,
+    pub pull_requests: Option<String>,
+}
+
+impl State {
+
+    /// Location of the state, `path` being the repository's path within the provider (`OWNER/REPO`)
+    pub fn path<S: AsRef<str>>(repo: &sit_core::Repository, provider: S, path: S) -> PathBuf {
+        repo.path().join("import").join(provider.as_ref())
+            .join(format!("{}.json", path.as_ref()))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::File::open(path) {
+            Ok(file) => serde_json::from_reader(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(State::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+}
+
+/// Advances a high-water mark if `timestamp` is newer
+///
+/// Relies on the source always formatting timestamps the same way
+/// (`YYYY-MM-DDTHH:MM:SS[.sss]Z`), which makes them comparable lexicographically.
+pub fn advance(mark: &mut Option<String>, timestamp: &str) {
+    if mark.as_ref().map(|m| m.as_str() < timestamp).unwrap_or(true) {
+        *mark = Some(timestamp.into());
+    }
+}
+
+/// Identifies an imported record by its types, timestamp and `.imported` URL
+///
+/// `Commented` is left out when it comes along with other types, as whether some
+/// records are also shown as comments depends on the options of the import.
+fn fingerprint<S: AsRef<str>>(types: &mut Vec<S>, timestamp: &str, imported: &str) -> String {
+    if types.len() > 1 {
+        types.retain(|t| t.as_ref() != ".type/Commented");
+    }
+    types.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+    let types: Vec<_> = types.iter().map(|s| s.as_ref()).collect();
+    format!("{}\n{}\n{}", imported, timestamp, types.join(","))
+}
+
+/// Labels, assignees, milestone and state of an item as set by its records
+///
+/// Records are applied the way the issue-tracking module's reducers do.
+#[derive(Default, Debug)]
+pub struct Current {
+    pub labels: Vec<String>,
+    /// Colors of the labels that have them, by name
+    pub label_colors: HashMap<String, String>,
+    pub assignees: Vec<String>,
+    pub milestone: Option<String>,
+    pub closed: bool,
+}
+
+impl Current {
+
+    fn apply<F>(&mut self, types: &[&str], file: F) -> io::Result<()> where F: Fn(&str) -> io::Result<Option<String>> {
+        let is = |type_: &str| types.iter().any(|t| *t == type_);
+        let lines = |text: String| -> Vec<String> { text.split('\n').filter(|s| !s.is_empty()).map(String::from).collect() };
+        if is("Tagged") || is("Untagged") || is("TagsChanged") {
+            if let Some(labels) = file("labels")? {
+                let colors: Vec<_> = file("colors")?.map(|colors| colors.split('\n').map(String::from).collect()).unwrap_or_default();
+                if is("TagsChanged") {
+                    self.labels.clear();
+                }
+                for (index, label) in lines(labels).into_iter().enumerate() {
+                    if is("Untagged") {
+                        self.labels.retain(|l| *l != label);
+                        continue;
+                    }
+                    match colors.get(index) {
+                        Some(color) if !color.is_empty() => {
+                            self.label_colors.insert(label.clone(), color.clone());
+                        },
+                        _ => (),
+                    }
+                    if !self.labels.contains(&label) {
+                        self.labels.push(label);
+                    }
+                }
+            }
+        }
+        if is("Assigned") || is("Unassigned") || is("AssigneesChanged") {
+            if let Some(assignees) = file("assignees")? {
+                if is("AssigneesChanged") {
+                    self.assignees.clear();
+                }
+                for assignee in lines(assignees) {
+                    if is("Unassigned") {
+                        self.assignees.retain(|a| *a != assignee);
+                    } else if !self.assignees.contains(&assignee) {
+                        self.assignees.push(assignee);
+                    }
+                }
+            }
+        }
+        if is("Milestoned") {
+            self.milestone = file("milestone")?.map(|m| m.trim().to_string());
+        }
+        if is("Demilestoned") {
+            match file("milestone")? {
+                Some(ref milestone) if Some(milestone.trim()) != self.milestone.as_ref().map(String::as_str) => (),
+                _ => self.milestone = None,
+            }
+        }
+        if is("Closed") {
+            self.closed = true;
+        }
+        if is("Reopened") {
+            self.closed = false;
+        }
+        Ok(())
+    }
+
+}
+
+/// SIT item that is being imported into
+///
+/// Keeps track of records imported into it previously, so that
+/// importing the same source item again only appends what's new.
+pub struct Target<'a> {
+    name: String,
+    /// Absent only during a dry run, if the item doesn't exist yet
+    item: Option<sit_core::repository::Item<'a>>,
+    imported: HashSet<String>,
+    /// Hashes of the records of imported comments, by their URL
+    comments: HashMap<String, String>,
+    current: Current,
+    report: Option<Arc<Mutex<Report>>>,
+}
+
+impl<'a> Target<'a> {
+
+    /// Opens the item, creating it if necessary
+    ///
+    /// If a `report` is given, this is a dry run: nothing gets created and
+    /// new records are added to the report instead.
+    pub fn open<S: AsRef<str>>(repo: &'a sit_core::Repository, name: S, report: Option<&Arc<Mutex<Report>>>)
+                               -> Result<Self, sit_core::RepositoryError> {
+        let item = match repo.item(name.as_ref()) {
+            Some(item) => Some(item),
+            None if report.is_some() => None,
+            None => Some(repo.new_named_item(name.as_ref())?),
+        };
+        let mut imported = HashSet::new();
+        let mut comments = HashMap::new();
+        let mut current = Current::default();
+        if let Some(ref item) = item {
+            for record in item.record_iter()?.flat_map(|records| records) {
+                let mut types: Vec<String> = record.file_iter()
+                    .map(|(name, _)| name)
+                    .filter(|name| name.starts_with(".type/"))
+                    .collect();
+                {
+                    let names: Vec<_> = types.iter().map(|t| &t[6..]).collect();
+                    current.apply(&names, |name| read_file(&record, name))?;
+                }
+                let timestamp = read_file(&record, ".timestamp")?;
+                let url = read_file(&record, ".imported")?;
+                if let (Some(timestamp), Some(url)) = (timestamp, url) {
+                    if types.iter().any(|t| t == ".type/Commented") {
+                        comments.insert(url.clone(), record.encoded_hash());
+                    }
+                    imported.insert(fingerprint(&mut types, &timestamp, &url));
+                }
+            }
+        }
+        Ok(Target { name: name.as_ref().into(), item, imported, comments, current, report: report.cloned() })
+    }
+
+    /// Returns `true` if a record with given types, timestamp and `.imported` URL is already present
+    pub fn contains(&self, types: &[&str], timestamp: &str, imported: &str) -> bool {
+        let mut types: Vec<_> = types.iter().map(|t| format!(".type/{}", t)).collect();
+        self.imported.contains(&fingerprint(&mut types, timestamp, imported))
+    }
+
+    /// Hash of the record of a comment imported from given URL
+    ///
+    /// During a dry run, comments that would be imported have no record yet.
+    pub fn comment(&self, url: &str) -> Option<&String> {
+        self.comments.get(url)
+    }
+
+    /// Labels, assignees, milestone and state the item has as of the records imported so far
+    pub fn current(&self) -> &Current {
+        &self.current
+    }
+
+    /// Creates a new record unless it was imported before
+    pub fn new_record<'f>(&mut self, files: Files<&'f str, Box<Read + 'f>>) -> Result<bool, sit_core::RepositoryError> {
+        let mut buffered = vec![];
+        for (name, mut reader) in files {
+            let mut buf = vec![];
+            reader.read_to_end(&mut buf)?;
+            buffered.push((name, buf));
+        }
+        let timestamp = buffered.iter().find(|&&(name, _)| name == ".timestamp")
+            .map(|&(_, ref buf)| String::from_utf8_lossy(buf).into_owned());
+        let url = buffered.iter().find(|&&(name, _)| name == ".imported")
+            .map(|&(_, ref buf)| String::from_utf8_lossy(buf).into_owned());
+        let comment = buffered.iter().any(|&(name, _)| name == ".type/Commented");
+        if let (Some(timestamp), Some(url)) = (timestamp, url.as_ref()) {
+            let mut types: Vec<_> = buffered.iter().map(|&(name, _)| name)
+                .filter(|name| name.starts_with(".type/")).collect();
+            if !self.imported.insert(fingerprint(&mut types, &timestamp, url)) {
+                return Ok(false);
+            }
+        }
+        {
+            let types: Vec<_> = buffered.iter().map(|&(name, _)| name)
+                .filter(|name| name.starts_with(".type/")).map(|name| &name[6..]).collect();
+            self.current.apply(&types, |name| Ok(buffered.iter().find(|&&(n, _)| n == name)
+                                                     .map(|&(_, ref buf)| String::from_utf8_lossy(buf).into_owned())))?;
+        }
+        if let Some(ref report) = self.report {
+            report.lock().unwrap().record(&self.name, self.item.is_some(), &buffered);
+            return Ok(true);
+        }
+        if let Some(ref item) = self.item {
+            let record = item.new_record(buffered.into_iter().map(|(name, buf)| (name, Cursor::new(buf))), true)?;
+            if let (true, Some(url)) = (comment, url) {
+                self.comments.insert(url, record.encoded_hash());
+            }
+        }
+        Ok(true)
+    }
+
+    /// Notes a source event that has no SIT counterpart
+    pub fn skip(&self, event: &str) {
+        if let Some(ref report) = self.report {
+            report.lock().unwrap().skipped(event);
+        }
+    }
+
+}
+
+fn read_file<R: Record>(record: &R, name: &str) -> io::Result<Option<String>> {
+    match record.file(name) {
+        None => Ok(None),
+        Some(mut reader) => {
+            let mut s = String::new();
+            reader.read_to_string(&mut s)?;
+            Ok(Some(s))
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::io::{Read, Write, BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json;
+
+/// Response served to requests whose query contains all of the `needles`
+pub struct Route {
+    pub needles: Vec<&'static str>,
+    pub status: u16,
+    pub headers: Vec<(&'static str, &'static str)>,
+    pub body: &'static str,
+}
+
+impl Route {
+    pub fn new(needles: Vec<&'static str>, body: &'static str) -> Self {
+        Route { needles, status: 200, headers: vec![], body }
+    }
+
+    pub fn failing(needles: Vec<&'static str>) -> Self {
+        Route { needles, status: 500, headers: vec![], body: "recorded failure" }
+    }
+
+    /// Adds a response header, such as paging information of REST APIs
+    pub fn header(mut self, name: &'static str, value: &'static str) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+}
+
+/// Local stand-in for a GraphQL endpoint or a REST API, replaying recorded responses
+///
+/// GraphQL requests are told apart by their query, others by their path and query string,
+/// which are both recorded as queries. Routes are tried in order, so more specific ones
+/// should go first.
+pub struct Server {
+    pub url: String,
+    queries: Arc<Mutex<Vec<String>>>,
+}
+
+impl Server {
+
+    pub fn start(routes: Vec<Route>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/graphql", listener.local_addr().unwrap());
+        let queries = Arc::new(Mutex::new(vec![]));
+        let received = queries.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if let Ok(stream) = stream {
+                    respond(stream, &routes, &received);
+                }
+            }
+        });
+        Server { url, queries }
+    }
+
+    /// Queries received so far
+    pub fn queries(&self) -> Vec<String> {
+        self.queries.lock().unwrap().clone()
+    }
+
+    /// Number of queries received so far that contain all of the `needles`
+    pub fn count(&self, needles: &[&str]) -> usize {
+        self.queries().iter().filter(|q| needles.iter().all(|n| q.contains(n))).count()
+    }
+
+}
+
+fn respond(mut stream: TcpStream, routes: &[Route], queries: &Mutex<Vec<String>>) {
+    let mut content_length = 0;
+    let mut body = vec![];
+    let mut target = String::new();
+    {
+        let mut reader = BufReader::new(&stream);
+        reader.read_line(&mut target).unwrap_or(0);
+        // `GET /path?query HTTP/1.1`
+        target = target.split_whitespace().nth(1).unwrap_or("").into();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                break;
+            }
+            let lowercase = line.to_lowercase();
+            if lowercase.starts_with("content-length:") {
+                content_length = lowercase[15..].trim().parse().unwrap_or(0);
+            }
+        }
+        body.resize(content_length, 0);
+        reader.read_exact(&mut body).unwrap();
+    }
+    let query = serde_json::from_slice::<serde_json::Value>(&body).ok()
+        .and_then(|request| request["query"].as_str().map(String::from))
+        .unwrap_or(target);
+    let (status, headers, response) = match routes.iter().find(|r| r.needles.iter().all(|n| query.contains(n))) {
+        Some(route) => (route.status, &route.headers[..], route.body),
+        None => (404, &[][..], "no recorded response"),
+    };
+    queries.lock().unwrap().push(query);
+    let headers: String = headers.iter().map(|&(name, value)| format!("{}: {}\r\n", name, value)).collect();
+    write!(stream, "HTTP/1.1 {} Recorded\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+           status, headers, response.len(), response).unwrap();
+}
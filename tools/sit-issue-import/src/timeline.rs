@@ -1,8 +1,12 @@
+use std::collections::HashMap;
 use std::fmt::Display;
 use askama::Template;
-use graphql::{Query as GraphQLQuery, Pageable, HasPageInfo, HasTotalCount, PageInfo, Paged};
+use graphql::{Query as GraphQLQuery, Pageable, HasPageInfo, HasTotalCount, PageInfo, Paged, Connection};
 use author::Author;
+use edits::UserContentEdit;
+use reactions::Reaction;
 use actor::Actor;
+use provider::Label;
 
 #[derive(Clone, Copy)]
 pub enum Kind {
@@ -18,7 +22,10 @@ pub struct Query<S> where S : AsRef<str> + Display {
     pub repository: S,
     pub kind: &'static str,
     pub after: Option<String>,
-    pub number: usize,
+    /// Issues or pull requests whose timelines are fetched
+    pub numbers: Vec<usize>,
+    /// Whether timelines are aliased by their numbers rather than just `issue`
+    pub batched: bool,
 }
 
 impl<S> Query<S> where S : AsRef<str> + Display {
@@ -27,7 +34,12 @@ impl<S> Query<S> where S : AsRef<str> + Display {
             Kind::Issue => "issue",
             Kind::PullRequest => "pullRequest",
         };
-        Query { owner, repository, after: None, kind, number }
+        Query { owner, repository, after: None, kind, numbers: vec![number], batched: false }
+    }
+
+    /// Query for the first pages of several timelines at once
+    pub fn batch(owner: S, repository: S, kind: Kind, numbers: Vec<usize>) -> Batch<S> {
+        Batch(Query { numbers, batched: true, ..Query::new(owner, repository, kind, 0) })
     }
 
 }
@@ -43,9 +55,29 @@ impl<S> Pageable for Query<S> where S : AsRef<str> + Display + Copy {
     type Item = TimelineItem;
     fn after(&self, cursor: String) -> Self {
         Query { owner: self.owner, repository: self.repository, after: Some(cursor),
-                kind: self.kind, number: self.number }
+                kind: self.kind, numbers: self.numbers.clone(), batched: self.batched }
+    }
+}
+
+/// First pages of several timelines, each aliased by its number
+pub struct Batch<S>(Query<S>) where S : AsRef<str> + Display;
+
+impl<S> GraphQLQuery for Batch<S> where S : AsRef<str> + Display + Copy {
+    type Result = BatchResponse;
+    fn query(&self) -> String {
+        self.0.render().unwrap()
     }
 }
+
+/// There's only one page, timelines that don't fit are paged through one by one
+impl<S> Pageable for Batch<S> where S : AsRef<str> + Display + Copy {
+    type Item = (usize, Paged<TimelineItem>);
+    fn after(&self, _cursor: String) -> Self {
+        Batch(Query { owner: self.0.owner, repository: self.0.repository, after: None,
+                      kind: self.0.kind, numbers: self.0.numbers.clone(), batched: true })
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(tag = "__typename")]
 pub enum Closer {
@@ -56,44 +88,92 @@ pub enum Closer {
     #[serde(rename_all = "camelCase")]
     PullRequest {
         number: usize,
+        url: String,
+        repository: RepositoryRef,
     }
 }
 
-impl<'a> Into<String> for &'a Closer {
-    fn into(self) -> String {
-        match self {
-            &Closer::Commit { ref oid } => format!("Closed with {}", oid),
-            &Closer::PullRequest { number } => format!("Closed with pull request {}", number),
-        }
-    }
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RepositoryRef {
+    /// `OWNER/REPO`
+    pub name_with_owner: String,
 }
 
-use files::Files;
-use std::io::{Read, Cursor};
-impl<'a> Into<Files<&'a str, Box<Read>>> for &'a Closer {
+/// Issue or pull request that refers to another one
+#[derive(Deserialize, Debug)]
+#[serde(tag = "__typename")]
+pub enum ReferenceSource {
+    #[serde(rename_all = "camelCase")]
+    Issue {
+        number: usize,
+        url: String,
+        repository: RepositoryRef,
+    },
+    #[serde(rename_all = "camelCase")]
+    PullRequest {
+        number: usize,
+        url: String,
+        repository: RepositoryRef,
+    },
+}
 
-    fn into(self) -> Files<&'a str, Box<Read>> {
-        let s: String = self.into();
-        Files(vec![(".type/Commented", Box::new(&b""[..])),
-                   ("text", Box::new(Cursor::new(s.into_bytes())))])
-    }
 
+#[derive(Deserialize, Debug)]
+pub struct Commit {
+    pub oid: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReviewCommentRef {
+    pub url: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct ReviewRef {
+    pub url: String,
+}
+
+/// Inline comment of a pull request review
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewComment {
+    pub url: String,
+    pub body: String,
+    pub created_at: String,
+    pub path: String,
+    pub original_position: usize,
+    pub diff_hunk: String,
+    pub original_commit: Option<Commit>,
+    pub reply_to: Option<ReviewCommentRef>,
+    pub pull_request_review: Option<ReviewRef>,
+    pub author: Option<Author>,
+}
 
 #[derive(Deserialize, Debug)]
 #[serde(tag = "__typename")]
+#[allow(clippy::large_enum_variant)]
 pub enum TimelineItem {
     Commit {},
     #[serde(rename_all = "camelCase")]
     IssueComment {
+        id: String,
         url: String,
         body: String,
         created_at: String,
         updated_at: String,
         author: Option<Author>,
+        user_content_edits: Option<Connection<UserContentEdit>>,
+        reactions: Option<Connection<Reaction>>,
+    },
+    #[serde(rename_all = "camelCase")]
+    CrossReferencedEvent {
+        id: String,
+        database_id: Option<u64>,
+        actor: Option<Actor>,
+        created_at: String,
+        source: ReferenceSource,
     },
-    CrossReferencedEvent {},
     #[serde(rename_all = "camelCase")]
     ClosedEvent {
         actor: Option<Actor>,
@@ -112,14 +192,72 @@ pub enum TimelineItem {
     },
     SubscribedEvent {},
     UnsubscribedEvent {},
-    ReferencedEvent {},
-    AssignedEvent {},
-    UnassignedEvent {},
-    LabeledEvent {},
-    UnlabeledEvent {},
-    MilestonedEvent {},
-    DemilestonedEvent {},
-    RenamedTitleEvent {},
+    #[serde(rename_all = "camelCase")]
+    ReferencedEvent {
+        id: String,
+        database_id: Option<u64>,
+        actor: Option<Actor>,
+        created_at: String,
+        commit: Option<Commit>,
+        commit_repository: RepositoryRef,
+    },
+    #[serde(rename_all = "camelCase")]
+    AssignedEvent {
+        id: String,
+        database_id: Option<u64>,
+        actor: Option<Actor>,
+        created_at: String,
+        assignee: Option<Actor>,
+    },
+    #[serde(rename_all = "camelCase")]
+    UnassignedEvent {
+        id: String,
+        database_id: Option<u64>,
+        actor: Option<Actor>,
+        created_at: String,
+        assignee: Option<Actor>,
+    },
+    #[serde(rename_all = "camelCase")]
+    LabeledEvent {
+        id: String,
+        database_id: Option<u64>,
+        actor: Option<Actor>,
+        created_at: String,
+        label: Label,
+    },
+    #[serde(rename_all = "camelCase")]
+    UnlabeledEvent {
+        id: String,
+        database_id: Option<u64>,
+        actor: Option<Actor>,
+        created_at: String,
+        label: Label,
+    },
+    #[serde(rename_all = "camelCase")]
+    MilestonedEvent {
+        id: String,
+        database_id: Option<u64>,
+        actor: Option<Actor>,
+        created_at: String,
+        milestone_title: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    DemilestonedEvent {
+        id: String,
+        database_id: Option<u64>,
+        actor: Option<Actor>,
+        created_at: String,
+        milestone_title: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    RenamedTitleEvent {
+        id: String,
+        database_id: Option<u64>,
+        actor: Option<Actor>,
+        created_at: String,
+        previous_title: String,
+        current_title: String,
+    },
     #[serde(rename_all = "camelCase")]
     LockedEvent {
         actor: Option<Actor>,
@@ -139,12 +277,63 @@ pub enum TimelineItem {
     ReviewDismissedEvent {},
     DeployedEvent {},
     CommitCommentThread {},
-    PullRequestReview {},
-    PullRequestReviewThread {},
-    PullRequestReviewComment {},
+    #[serde(rename_all = "camelCase")]
+    PullRequestReview {
+        id: String,
+        url: String,
+        state: String,
+        body: String,
+        created_at: String,
+        author: Option<Author>,
+        comments: Connection<ReviewComment>,
+    },
+    #[serde(rename_all = "camelCase")]
+    PullRequestReviewThread {
+        id: String,
+        comments: Connection<ReviewComment>,
+    },
+    PullRequestReviewComment(ReviewComment),
 
 }
 
+impl TimelineItem {
+    /// GraphQL type name of the item
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            &TimelineItem::Commit { .. } => "Commit",
+            &TimelineItem::IssueComment { .. } => "IssueComment",
+            &TimelineItem::CrossReferencedEvent { .. } => "CrossReferencedEvent",
+            &TimelineItem::ClosedEvent { .. } => "ClosedEvent",
+            &TimelineItem::MergedEvent { .. } => "MergedEvent",
+            &TimelineItem::ReopenedEvent { .. } => "ReopenedEvent",
+            &TimelineItem::SubscribedEvent { .. } => "SubscribedEvent",
+            &TimelineItem::UnsubscribedEvent { .. } => "UnsubscribedEvent",
+            &TimelineItem::ReferencedEvent { .. } => "ReferencedEvent",
+            &TimelineItem::AssignedEvent { .. } => "AssignedEvent",
+            &TimelineItem::UnassignedEvent { .. } => "UnassignedEvent",
+            &TimelineItem::LabeledEvent { .. } => "LabeledEvent",
+            &TimelineItem::UnlabeledEvent { .. } => "UnlabeledEvent",
+            &TimelineItem::MilestonedEvent { .. } => "MilestonedEvent",
+            &TimelineItem::DemilestonedEvent { .. } => "DemilestonedEvent",
+            &TimelineItem::RenamedTitleEvent { .. } => "RenamedTitleEvent",
+            &TimelineItem::LockedEvent { .. } => "LockedEvent",
+            &TimelineItem::UnlockedEvent { .. } => "UnlockedEvent",
+            &TimelineItem::HeadRefDeletedEvent { .. } => "HeadRefDeletedEvent",
+            &TimelineItem::HeadRefRestoredEvent { .. } => "HeadRefRestoredEvent",
+            &TimelineItem::HeadRefForcePushedEvent { .. } => "HeadRefForcePushedEvent",
+            &TimelineItem::BaseRefForcePushedEvent { .. } => "BaseRefForcePushedEvent",
+            &TimelineItem::ReviewRequestedEvent { .. } => "ReviewRequestedEvent",
+            &TimelineItem::ReviewRequestedRemovedEvent { .. } => "ReviewRequestedRemovedEvent",
+            &TimelineItem::ReviewDismissedEvent { .. } => "ReviewDismissedEvent",
+            &TimelineItem::DeployedEvent { .. } => "DeployedEvent",
+            &TimelineItem::CommitCommentThread { .. } => "CommitCommentThread",
+            &TimelineItem::PullRequestReview { .. } => "PullRequestReview",
+            &TimelineItem::PullRequestReviewThread { .. } => "PullRequestReviewThread",
+            &TimelineItem::PullRequestReviewComment(_) => "PullRequestReviewComment",
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct TimelineNode {
@@ -184,3 +373,84 @@ impl IntoIterator for Response {
     }
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResponse {
+    /// Timelines by alias, those of missing issues are null
+    repository: HashMap<String, Option<TimelineNode>>,
+    #[serde(skip)]
+    page_info: PageInfo,
+}
+
+impl HasPageInfo for BatchResponse {
+    fn page_info(&self) -> &PageInfo {
+        &self.page_info
+    }
+}
+
+impl HasTotalCount for BatchResponse {
+    fn total_count(&self) -> usize {
+        self.repository.len()
+    }
+}
+
+impl IntoIterator for BatchResponse {
+    type Item = (usize, Paged<TimelineItem>);
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut timelines: Vec<_> = self.repository.into_iter()
+            .filter_map(|(alias, node)| match (alias.trim_left_matches("issue").parse(), node) {
+                (Ok(number), Some(node)) => Some((number, node.timeline)),
+                _ => None,
+            }).collect();
+        timelines.sort_by_key(|&(number, _)| number);
+        timelines.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn deserialize() {
+        let page: serde_json::Value = serde_json::from_str(include_str!("../fixtures/github/timeline_1.json")).unwrap();
+        let response: Response = serde_json::from_value(page["data"].clone()).unwrap();
+        let items: Vec<_> = response.into_iter().collect();
+        assert_eq!(items.len(), 3);
+        match items[0] {
+            TimelineItem::IssueComment { ref body, ref author, .. } => {
+                assert_eq!(body, "On it");
+                assert_eq!(author.as_ref().map(|a| a.login.as_str()), Some("octocat"));
+            },
+            ref item => panic!("unexpected item {:?}", item),
+        }
+        assert_eq!(items[1].type_name(), "SubscribedEvent");
+        match items[2] {
+            TimelineItem::ClosedEvent { closer: Some(Closer::Commit { ref oid }), .. } => {
+                assert_eq!(oid, "3f8c2a");
+            },
+            ref item => panic!("unexpected item {:?}", item),
+        }
+    }
+
+    #[test]
+    fn batch() {
+        let text = GraphQLQuery::query(&Query::batch("octo-org", "octoroku", Kind::PullRequest, vec![1, 2]));
+        assert!(text.contains("issue1: pullRequest(number: 1)"));
+        assert!(text.contains("issue2: pullRequest(number: 2)"));
+        assert!(!text.contains("issue: "));
+
+        let page: serde_json::Value = serde_json::from_str(include_str!("../fixtures/github/timelines.json")).unwrap();
+        let response: BatchResponse = serde_json::from_value(page["data"].clone()).unwrap();
+        assert!(!response.page_info().has_next_page);
+        let timelines: Vec<_> = response.into_iter().collect();
+        assert_eq!(timelines.iter().map(|&(number, _)| number).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert!(timelines[0].1.page_info.has_next_page);
+        assert_eq!(timelines[0].1.nodes.len(), 1);
+    }
+
+}
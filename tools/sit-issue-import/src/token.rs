@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use serde_yaml;
+use xdg;
+
+/// Where a token was found
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    Environment(&'static str),
+    Config(PathBuf),
+    CredentialHelper,
+    GitHubCli(PathBuf),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Source::Environment(var) => write!(f, "the {} environment variable", var),
+            &Source::Config(ref path) => write!(f, "{}", path.display()),
+            &Source::CredentialHelper => write!(f, "the git credential helper"),
+            &Source::GitHubCli(ref path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Default location of the configuration file
+pub fn default_config() -> Option<PathBuf> {
+    xdg::BaseDirectories::with_prefix("sit-issue-import").ok()?.find_config_file("config.json")
+}
+
+/// Finds a GitHub token for the given host
+///
+/// Sources are tried in this order:
+///
+/// * the configuration file (`configured`), as it's given explicitly
+/// * `GITHUB_TOKEN` and `GH_TOKEN` environment variables for github.com,
+///   `GITHUB_ENTERPRISE_TOKEN` and `GH_ENTERPRISE_TOKEN` for other hosts
+/// * the git credential helper
+/// * the GitHub CLI's `hosts.yml`
+pub fn github(host: &str, configured: Option<(String, PathBuf)>) -> Option<(String, Source)> {
+    configured.map(|(token, path)| (token, Source::Config(path)))
+        .or_else(|| environment(host, |var| env::var(var).ok()))
+        .or_else(|| credential_helper(host).map(|token| (token, Source::CredentialHelper)))
+        .or_else(|| github_cli(host))
+}
+
+fn environment<F: Fn(&str) -> Option<String>>(host: &str, var: F) -> Option<(String, Source)> {
+    let vars = if host == "github.com" { ["GITHUB_TOKEN", "GH_TOKEN"] } else { ["GITHUB_ENTERPRISE_TOKEN", "GH_ENTERPRISE_TOKEN"] };
+    vars.iter()
+        .filter_map(|&name| var(name).and_then(non_empty).map(|token| (token, Source::Environment(name))))
+        .next()
+}
+
+fn non_empty(s: String) -> Option<String> {
+    if s.trim().is_empty() { None } else { Some(s.trim().into()) }
+}
+
+fn credential_helper(host: &str) -> Option<String> {
+    let mut child = Command::new("git").args(&["credential", "fill"])
+        // Only ask configured helpers, never the user
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_ASKPASS", "")
+        .env("SSH_ASKPASS", "")
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::null())
+        .spawn().ok()?;
+    write!(child.stdin.take()?, "protocol=https\nhost={}\n\n", host).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    password(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extracts the password from `git credential fill` output
+fn password(output: &str) -> Option<String> {
+    output.lines().filter_map(|line| if line.starts_with("password=") { non_empty(line[9..].into()) } else { None }).next()
+}
+
+#[derive(Deserialize)]
+struct GitHubCliHost {
+    oauth_token: Option<String>,
+}
+
+fn github_cli(host: &str) -> Option<(String, Source)> {
+    let path = match env::var_os("GH_CONFIG_DIR") {
+        Some(dir) => PathBuf::from(dir).join("hosts.yml"),
+        None => xdg::BaseDirectories::with_prefix("gh").ok()?.find_config_file("hosts.yml")?,
+    };
+    let mut hosts = String::new();
+    fs::File::open(&path).ok()?.read_to_string(&mut hosts).ok()?;
+    oauth_token(&hosts, host).map(|token| (token, Source::GitHubCli(path)))
+}
+
+/// Extracts the token of a host from the GitHub CLI's `hosts.yml`
+///
+/// Recent versions keep tokens in the system keyring, in which case there's none.
+fn oauth_token(hosts: &str, host: &str) -> Option<String> {
+    let mut hosts: HashMap<String, GitHubCliHost> = serde_yaml::from_str(hosts).ok()?;
+    hosts.remove(host)?.oauth_token.and_then(non_empty)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn precedence() {
+        let configured = Some(("configured".to_string(), PathBuf::from("config.json")));
+        assert_eq!(github("github.com", configured), Some(("configured".into(), Source::Config(PathBuf::from("config.json")))));
+
+        let vars = |name: &str| match name {
+            "GH_TOKEN" => Some("gh".to_string()),
+            "GITHUB_TOKEN" => Some(" ".to_string()),
+            _ => None,
+        };
+        assert_eq!(environment("github.com", &vars), Some(("gh".into(), Source::Environment("GH_TOKEN"))));
+        assert_eq!(environment("ghe.example.com", &vars), None);
+    }
+
+    #[test]
+    fn credential_output() {
+        assert_eq!(password("protocol=https\nhost=github.com\nusername=octocat\npassword=secret\n"), Some("secret".into()));
+        assert_eq!(password("protocol=https\nhost=github.com\n"), None);
+    }
+
+    #[test]
+    fn github_cli_hosts() {
+        let hosts = "github.com:\n    user: octocat\n    oauth_token: gho_secret\n    git_protocol: https\n\
+                     ghe.example.com:\n    user: octocat\n    git_protocol: ssh\n";
+        assert_eq!(oauth_token(hosts, "github.com"), Some("gho_secret".into()));
+        assert_eq!(oauth_token(hosts, "ghe.example.com"), None);
+        assert_eq!(oauth_token(hosts, "example.com"), None);
+    }
+
+}
@@ -0,0 +1,400 @@
+use std::io::{Read, Cursor};
+
+use sit_core::RepositoryError;
+
+use error::Error;
+
+use files::Files;
+use provider::{Provider, Kind, Item, Event, Label, Milestone, Edit, Reaction, Closer};
+use sync::Target;
+
+fn authors<'a>(author: Option<&'a String>) -> Files<&'a str, Box<Read + 'a>> {
+    match author {
+        None => Files(vec![]),
+        Some(author) => Files::from(vec![(".authors", author.as_bytes())]),
+    }
+}
+
+/// Records reactions, to a comment if the hash of its record is given or to the item itself otherwise
+fn reactions(target: &mut Target, reactions: &[Reaction], comment: Option<&String>) -> Result<(), RepositoryError> {
+    for reaction in reactions {
+        let mut rec = vec![(".type/Reacted", &b""[..]),
+                           (".timestamp", reaction.created_at.as_bytes()),
+                           ("reaction", reaction.content.as_bytes()),
+                           (".imported", reaction.url.as_bytes())];
+        if let Some(comment) = comment {
+            rec.push(("comment", comment.as_bytes()));
+        }
+        target.new_record(Files::from(rec).followed_by(authors(reaction.author.as_ref())))?;
+    }
+    Ok(())
+}
+
+/// Records the original summary of an issue or a merge request, unless it was recorded before
+fn original_summary(target: &mut Target, item: &Item, title: &str) -> Result<(), RepositoryError> {
+    if !target.contains(&["SummaryChanged"], &item.created_at, &item.url) {
+        target.new_record(Files::from(vec![(".type/SummaryChanged", &b""[..]),
+                                           (".timestamp", item.created_at.as_bytes()),
+                                           ("text", title.as_bytes()),
+                                           (".imported", item.url.as_bytes())])
+                              .followed_by(authors(item.author.as_ref())))?;
+    }
+    Ok(())
+}
+
+/// Records the details of an issue or a merge request, followed by their later revisions
+/// and reactions to the issue or the merge request
+///
+/// The summary is recorded once its original value is known, either by [`event`] when the
+/// first rename is encountered or by [`snapshot`].
+///
+/// The patch of a merge request is only retrieved if it wasn't imported before.
+pub fn item(target: &mut Target, provider: &Provider, item: &Item) -> Result<(), Error> {
+    match item.kind {
+        Kind::Issue => {
+            target.new_record(Files::from(vec![(".type/DetailsChanged", &b""[..]),
+                                               (".timestamp", item.created_at.as_bytes()),
+                                               ("text", item.body.as_bytes()),
+                                               (".imported", item.url.as_bytes())])
+                                  .followed_by(authors(item.author.as_ref())))?;
+        },
+        Kind::MergeRequest => {
+            if !target.contains(&["DetailsChanged", "MergeRequested"], &item.created_at, &item.url) {
+                let patch = provider.patch(item)?;
+
+                let mut mr_rec = vec![(".type/DetailsChanged", &b""[..]),
+                                      (".type/MergeRequested", &b""[..]),
+                                      (".timestamp", item.created_at.as_bytes()),
+                                      ("text", item.body.as_bytes()),
+                                      (".imported", item.url.as_bytes())];
+
+                if let Some((name, ref patch)) = patch {
+                    mr_rec.push((name, patch.as_bytes()));
+                }
+
+                target.new_record(Files::from(mr_rec).followed_by(authors(item.author.as_ref())))?;
+            }
+        },
+    }
+    for edit in &item.edits {
+        target.new_record(Files::from(vec![(".type/DetailsChanged", &b""[..]),
+                                           (".timestamp", edit.edited_at.as_bytes()),
+                                           ("text", edit.text.as_bytes()),
+                                           (".imported", item.url.as_bytes())])
+                              .followed_by(authors(edit.editor.as_ref())))?;
+    }
+    reactions(target, &item.reactions, None)?;
+    Ok(())
+}
+
+/// Records a milestone as it currently is
+///
+/// Records are timestamped with the time of the last update, so that every
+/// change made to the milestone since the previous import is reflected.
+pub fn milestone(target: &mut Target, milestone: &Milestone) -> Result<(), RepositoryError> {
+    target.new_record(Files::from(vec![(".type/SummaryChanged", &b""[..]),
+                                       (".timestamp", milestone.updated_at.as_bytes()),
+                                       ("text", milestone.title.as_bytes()),
+                                       (".imported", milestone.url.as_bytes())])
+                          .followed_by(authors(milestone.author.as_ref())))?;
+
+    target.new_record(Files::from(vec![(".type/DetailsChanged", &b""[..]),
+                                       (".timestamp", milestone.updated_at.as_bytes()),
+                                       ("text", milestone.description.as_bytes()),
+                                       (".imported", milestone.url.as_bytes())])
+                          .followed_by(authors(milestone.author.as_ref())))?;
+
+    let mut rec = vec![(".type/Milestone", &b""[..]),
+                       (".timestamp", milestone.updated_at.as_bytes()),
+                       (".imported", milestone.url.as_bytes())];
+    if let Some(ref due_on) = milestone.due_on {
+        rec.push(("due", due_on.as_bytes()));
+    }
+    target.new_record(Files::from(rec).followed_by(authors(milestone.author.as_ref())))?;
+
+    match milestone.closed_at {
+        Some(ref closed_at) if milestone.closed => {
+            target.new_record(Files::from(vec![(".type/Closed", &b""[..]),
+                                               (".timestamp", closed_at.as_bytes()),
+                                               (".imported", milestone.url.as_bytes())]))?;
+        },
+        _ if !milestone.closed && target.current().closed => {
+            target.new_record(Files::from(vec![(".type/Reopened", &b""[..]),
+                                               (".timestamp", milestone.updated_at.as_bytes()),
+                                               (".imported", milestone.url.as_bytes())]))?;
+        },
+        _ => (),
+    }
+    Ok(())
+}
+
+fn milestone_files<'a>(title: Option<&'a String>, milestone: Option<&'a String>) -> Files<&'a str, Box<Read + 'a>> {
+    let mut files = vec![];
+    if let Some(title) = title {
+        files.push(("title", title.as_bytes()));
+    }
+    if let Some(milestone) = milestone {
+        files.push(("milestone", milestone.as_bytes()));
+    }
+    Files::from(files)
+}
+
+fn label_files<'a>(labels: &'a [Label]) -> Files<&'a str, Box<Read + 'a>> {
+    let names: Vec<_> = labels.iter().map(|l| l.name.as_str()).collect();
+    let mut files = vec![("labels", names.join("\n"))];
+    if labels.iter().all(|l| l.color.is_some()) {
+        let colors: Vec<_> = labels.iter().filter_map(|l| l.color.as_ref()).map(String::as_str).collect();
+        files.push(("colors", colors.join("\n")));
+    }
+    Files::from(files.into_iter().map(|(name, s)| (name, Cursor::new(s.into_bytes()))).collect::<Vec<_>>())
+}
+
+fn assignee_files<'a, S: AsRef<str>>(assignees: &[S]) -> Files<&'a str, Box<Read + 'a>> {
+    let assignees: Vec<_> = assignees.iter().map(|s| s.as_ref()).collect();
+    Files::from(vec![("assignees", Cursor::new(assignees.join("\n").into_bytes()))])
+}
+
+/// Records the labels, assignees and milestone an issue or a merge request currently has,
+/// replacing ones set before, where they differ from what the imported records set
+///
+/// Written after the history is imported, so that the item ends up in sync with the source
+/// even if its history is incomplete. If the item was never renamed, its summary is recorded here, too.
+pub fn snapshot(target: &mut Target, item: &Item) -> Result<(), RepositoryError> {
+    original_summary(target, item, &item.title)?;
+    if let Some(ref labels) = item.labels {
+        let changed = {
+            let current = target.current();
+            !same(labels.iter().map(|l| &l.name), &current.labels) ||
+                labels.iter().any(|l| l.color.is_some() && l.color.as_ref() != current.label_colors.get(&l.name))
+        };
+        if changed {
+            target.new_record(Files::from(vec![(".type/TagsChanged", &b""[..]),
+                                               (".timestamp", item.updated_at.as_bytes()),
+                                               (".imported", item.url.as_bytes())])
+                                  .followed_by(label_files(labels)))?;
+        }
+    }
+    if let Some(ref assignees) = item.assignees {
+        if !same(assignees.iter(), &target.current().assignees) {
+            target.new_record(Files::from(vec![(".type/AssigneesChanged", &b""[..]),
+                                               (".timestamp", item.updated_at.as_bytes()),
+                                               (".imported", item.url.as_bytes())])
+                                  .followed_by(assignee_files(assignees)))?;
+        }
+    }
+    if item.milestone != target.current().milestone {
+        match item.milestone {
+            Some(ref milestone) => {
+                target.new_record(Files::from(vec![(".type/Milestoned", &b""[..]),
+                                                   (".timestamp", item.updated_at.as_bytes()),
+                                                   (".imported", item.url.as_bytes())])
+                                      .followed_by(milestone_files(None, Some(milestone))))?;
+            },
+            None => {
+                target.new_record(Files::from(vec![(".type/Demilestoned", &b""[..]),
+                                                   (".timestamp", item.updated_at.as_bytes()),
+                                                   (".imported", item.url.as_bytes())]))?;
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if both have the same names, in any order
+fn same<'a, I: Iterator<Item = &'a String>>(names: I, current: &[String]) -> bool {
+    let mut names: Vec<_> = names.collect();
+    let mut current: Vec<_> = current.iter().collect();
+    names.sort();
+    current.sort();
+    names == current
+}
+
+/// Records an entry of the history of an issue or a merge request
+///
+/// With `closer_comments`, what closed the issue or the merge request is also recorded as a comment.
+pub fn event(target: &mut Target, item: &Item, event: &Event, closer_comments: bool) -> Result<(), RepositoryError> {
+    let url = item.url.as_bytes();
+    match event {
+        &Event::Commented { ref url, ref body, ref created_at, ref author, ref edits, reactions: ref reacted } => {
+            target.new_record(Files::from(vec![(".type/Commented", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               ("text", body.as_bytes()),
+                                               (".imported", url.as_bytes())])
+                                  .followed_by(authors(author.as_ref())))?;
+            // Revisions and reactions refer to the comment by its record,
+            // which a dry run doesn't create, so its URL stands in for it then
+            let comment = target.comment(url).cloned().unwrap_or_else(|| url.clone());
+            for &Edit { ref edited_at, ref editor, ref text } in edits {
+                target.new_record(Files::from(vec![(".type/CommentEdited", &b""[..]),
+                                                   (".timestamp", edited_at.as_bytes()),
+                                                   ("text", text.as_bytes()),
+                                                   ("comment", comment.as_bytes()),
+                                                   (".imported", url.as_bytes())])
+                                      .followed_by(authors(editor.as_ref())))?;
+            }
+            reactions(target, reacted, Some(&comment))?;
+        },
+        &Event::Assigned { ref url, ref created_at, ref actor, ref assignee } => {
+            target.new_record(Files::from(vec![(".type/Assigned", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url.as_bytes())])
+                                  .followed_by(assignee_files(&[assignee]))
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Unassigned { ref url, ref created_at, ref actor, ref assignee } => {
+            target.new_record(Files::from(vec![(".type/Unassigned", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url.as_bytes())])
+                                  .followed_by(assignee_files(&[assignee]))
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Reviewed { ref url, ref created_at, ref author, ref state, ref body } => {
+            // Reviews without a body are shown as a comment stating the outcome
+            let text = if body.trim().is_empty() {
+                match state.as_str() {
+                    "APPROVED" => "Approved",
+                    "CHANGES_REQUESTED" => "Requested changes",
+                    "DISMISSED" => "Review dismissed",
+                    _ => "Reviewed",
+                }
+            } else {
+                body.as_str()
+            };
+            target.new_record(Files::from(vec![(".type/Reviewed", &b""[..]),
+                                               (".type/Commented", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               ("state", state.as_bytes()),
+                                               ("text", text.as_bytes()),
+                                               (".imported", url.as_bytes())])
+                                  .followed_by(authors(author.as_ref())))?;
+        },
+        &Event::ReviewCommented { ref url, ref created_at, ref author, ref body, ref review, ref path, position,
+                                  ref diff_hunk, ref commit, ref in_reply_to } => {
+            let position = position.to_string();
+            let mut rec = vec![(".type/ReviewCommented", &b""[..]),
+                               (".type/Commented", &b""[..]),
+                               (".timestamp", created_at.as_bytes()),
+                               ("text", body.as_bytes()),
+                               ("path", path.as_bytes()),
+                               ("position", position.as_bytes()),
+                               ("diff_hunk", diff_hunk.as_bytes()),
+                               (".imported", url.as_bytes())];
+            if let &Some(ref review) = review {
+                rec.push(("review", review.as_bytes()));
+            }
+            if let &Some(ref commit) = commit {
+                rec.push(("commit", commit.as_bytes()));
+            }
+            if let &Some(ref in_reply_to) = in_reply_to {
+                rec.push(("in_reply_to", in_reply_to.as_bytes()));
+            }
+            target.new_record(Files::from(rec).followed_by(authors(author.as_ref())))?;
+        },
+        &Event::Renamed { ref url, ref created_at, ref actor, ref previous_title, ref current_title } => {
+            original_summary(target, item, previous_title)?;
+            target.new_record(Files::from(vec![(".type/SummaryChanged", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               ("text", current_title.as_bytes()),
+                                               (".imported", url.as_bytes())])
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Milestoned { ref url, ref created_at, ref actor, ref title, ref milestone } => {
+            target.new_record(Files::from(vec![(".type/Milestoned", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url.as_bytes())])
+                                  .followed_by(milestone_files(Some(title), milestone.as_ref()))
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Demilestoned { ref url, ref created_at, ref actor, ref title, ref milestone } => {
+            target.new_record(Files::from(vec![(".type/Demilestoned", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url.as_bytes())])
+                                  .followed_by(milestone_files(Some(title), milestone.as_ref()))
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Labeled { ref url, ref created_at, ref actor, ref label } => {
+            let labels = [label.clone()];
+            target.new_record(Files::from(vec![(".type/Tagged", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url.as_bytes())])
+                                  .followed_by(label_files(&labels))
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Unlabeled { ref url, ref created_at, ref actor, ref label } => {
+            let labels = [label.clone()];
+            target.new_record(Files::from(vec![(".type/Untagged", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url.as_bytes())])
+                                  .followed_by(label_files(&labels))
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Closed { ref created_at, ref actor, ref closer } => {
+            let text = closer.as_ref().map(|closer| closer.to_string());
+            let mut rec = vec![(".type/Closed", &b""[..]),
+                               (".timestamp", created_at.as_bytes()),
+                               (".imported", url)];
+            match closer {
+                &Some(Closer::Commit(ref oid)) => rec.push(("closer/commit", oid.as_bytes())),
+                &Some(Closer::MergeRequest { ref url, ref item }) => {
+                    rec.push(("closer/pull-request", url.as_bytes()));
+                    if let &Some(ref item) = item {
+                        rec.push(("closer/pull-request-item", item.as_bytes()));
+                    }
+                },
+                &None => (),
+            }
+            match text {
+                Some(ref text) if closer_comments => {
+                    rec.push((".type/Commented", &b""[..]));
+                    rec.push(("text", text.as_bytes()));
+                },
+                _ => (),
+            }
+            target.new_record(Files::from(rec).followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Reopened { ref created_at, ref actor } => {
+            target.new_record(Files::from(vec![(".type/Reopened", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url)])
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Locked { ref created_at, ref actor } => {
+            target.new_record(Files::from(vec![(".type/Locked", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url)])
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Unlocked { ref created_at, ref actor } => {
+            target.new_record(Files::from(vec![(".type/Unlocked", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url)])
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Merged { ref created_at, ref actor } => {
+            target.new_record(Files::from(vec![(".type/Closed", &b""[..]),
+                                               (".type/Merged", &b""[..]),
+                                               (".timestamp", created_at.as_bytes()),
+                                               (".imported", url)])
+                                  .followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Linked { ref url, ref created_at, ref actor, ref relation, ref item, target: ref linked, ref commit, ref text } => {
+            let mut rec = vec![(".type/Linked", &b""[..]),
+                               (".timestamp", created_at.as_bytes()),
+                               ("relation", relation.as_bytes()),
+                               ("url", linked.as_bytes()),
+                               (".imported", url.as_bytes())];
+            if let &Some(ref item) = item {
+                rec.push(("item", item.as_bytes()));
+            }
+            if let &Some(ref commit) = commit {
+                rec.push(("commit", commit.as_bytes()));
+            }
+            if let &Some(ref text) = text {
+                rec.push(("text", text.as_bytes()));
+            }
+            target.new_record(Files::from(rec).followed_by(authors(actor.as_ref())))?;
+        },
+        &Event::Skipped { ref event } => target.skip(event),
+    }
+    Ok(())
+}